@@ -13,13 +13,18 @@ macro_rules! arg_macro {
             $name: ident,
             [ $($alias: literal),+ ],
             $description: literal,
-            [ $($argument: ty),* ]
+            [ $($argument: ty $({ $($key: ident : $value: expr),+ })?),* ]
         }),*],
         aliases: [$(
             $alias_name: ident : {
                 $(($($aliased_value: tt)+) => $aliased_exp: expr),+
             }
         ),*]
+        $(, extra: [$({
+            $extra_name: ident,
+            $extra_description: literal,
+            $extra_argument: ty
+        }),+])?
     ) => { paste! {
         /// Holds runtime-accessible data about every variant supported by Chilly.
         pub static $dataname: [RuntimeData<[< $datakind Name >]>; arg_macro!(count $($name)*)] = [
@@ -28,7 +33,7 @@ macro_rules! arg_macro {
                     name: [< $datakind Name >]::$name,
                     aliases: &[ $($alias),+ ],
                     description: $description,
-                    arguments: &[ $(stringify!($argument)),* ]
+                    arguments: &[ $(arg_macro!(schema $argument $({ $($key: $value),+ })?)),* ]
                 }
             ),*
         ];
@@ -52,6 +57,12 @@ macro_rules! arg_macro {
                     _ => return None
                 } )
             }
+
+            #[doc = concat!("Gets the runtime-accessible data about this ", stringify!($datakind), ".")]
+            #[must_use]
+            pub fn data(self) -> &'static RuntimeData<[< $datakind Name >]> {
+                &$dataname[self as usize]
+            }
         }
 
         impl std::fmt::Display for [< $datakind Name >] {
@@ -70,6 +81,11 @@ macro_rules! arg_macro {
                 #[doc = $description]
                 $name ( $($argument),* )
             ),*
+            // Extra variants have no canonical name, so they can only be made at runtime
+            $($(,
+                #[doc = $extra_description]
+                $extra_name ( $extra_argument )
+            )+)?
         }
 
         impl $datakind {
@@ -77,8 +93,16 @@ macro_rules! arg_macro {
             #[doc = concat!("Parses a ", stringify!($datakind), " from its canonical name and a list of arguments.")]
             ///
             /// # Errors
-            #[doc = concat!("Errors if a ", stringify!($datakind), "argument fails to parse.")]
-            pub fn parse<'a>(name: [< $datakind Name >], mut arguments: impl Iterator<Item = &'a str>) -> Result<$datakind, ArgumentError> {
+            #[doc = concat!(
+                "Errors if a ", stringify!($datakind),
+                " argument fails to parse, or is outside the bounds given in its schema."
+            )]
+            pub fn parse<'a>(name: [< $datakind Name >], arguments: impl Iterator<Item = &'a str>) -> Result<$datakind, ArgumentError> {
+                let arguments = arguments.collect::<Vec<_>>();
+                $crate::arguments::schema::validate_all(name.data().arguments, &arguments).map_err(
+                    |(index, err)| ArgumentError::InvalidArgument(stringify!($datakind), index, err)
+                )?;
+                let mut arguments = arguments.into_iter();
                 Ok( match name {
                     $([< $datakind Name >]::$name =>
                         {
@@ -105,6 +129,16 @@ macro_rules! arg_macro {
             }
         }
     } };
+    // Build the schema of an argument, overriding any fields given after it
+    (schema $ty: ty) => {
+        <$ty as $crate::arguments::Argument>::SCHEMA
+    };
+    (schema $ty: ty { $($key: ident : $value: expr),+ }) => {
+        $crate::arguments::ArgumentSchema {
+            $($key: Some($value),)+
+            ..<$ty as $crate::arguments::Argument>::SCHEMA
+        }
+    };
     // Hold a counter of tokens for help with the data slice
     (count $tt: tt $($tts: tt)*) => {
         1 + arg_macro!(count $($tts)*)
@@ -3,34 +3,24 @@
 use std::str::FromStr;
 use anyhow::anyhow;
 use crate::database::structures::Color;
+use super::schema::{ArgumentKind, ArgumentSchema};
 
-mod sealed {
-    use crate::database::structures::Color;
-    use super::{MetaKernel, TilingDirection};
-
-    pub trait Sealed {}
-    impl Sealed for MetaKernel {}
-    impl Sealed for TilingDirection {}
-    impl Sealed for u8 {}
-    impl Sealed for i8 {}
-    impl Sealed for isize {}
-    impl Sealed for f32 {}
-    impl Sealed for Color {}
-    impl Sealed for String {}
-    impl<T: Sealed> Sealed for Option<T> {}
-    impl<T: Sealed> Sealed for Vec<T> {}
-    impl<const N: usize, T: Sealed> Sealed for [T; N] {}
-}
-
-type BoxedErr = Box<dyn std::error::Error>;
+pub(crate) type BoxedErr = Box<dyn std::error::Error>;
 
 /// A trait that dictates that this object is available for
 /// parsing as a variant argument.
 ///
+/// Implement this to use your own types as the arguments of a
+/// custom variant, registered in a [`VariantRegistry`](super::VariantRegistry).
+///
 /// # Notes
 /// - This trait is **not** object safe.
-/// - This trait is [sealed.](https://rust-lang.github.io/api-guidelines/future-proofing.html#sealed-traits-protect-against-downstream-implementations-c-sealed)
-pub trait Argument: sealed::Sealed + Sized {
+pub trait Argument: Sized {
+    /// A description of what this takes, used for validation and help text.
+    ///
+    /// Types that don't override this are described as taking a single value of an unknown kind.
+    const SCHEMA: ArgumentSchema = ArgumentSchema::new(ArgumentKind::Other);
+
     /// Parses values from the iterator until this type can be constructed.
     ///
     /// # Errors
@@ -41,9 +31,49 @@ pub trait Argument: sealed::Sealed + Sized {
 }
 
 
+/// A list of arguments that a custom variant takes, parsed one after another.
+///
+/// This is implemented for every [`Argument`], and for tuples of up to four of them.
+pub trait Arguments: Sized {
+    /// Descriptions of each argument, in order.
+    const SCHEMAS: &'static [ArgumentSchema];
+
+    /// Parses each argument in turn from the iterator.
+    ///
+    /// # Errors
+    /// Returns a [`Box<dyn std::error::Error>`] when any argument fails to parse.
+    fn parse<'a>(args: impl Iterator<Item = &'a str>) -> Result<Self, BoxedErr>;
+}
+
+impl<A: Argument> Arguments for A {
+    const SCHEMAS: &'static [ArgumentSchema] = &[A::SCHEMA];
+
+    fn parse<'a>(args: impl Iterator<Item = &'a str>) -> Result<Self, BoxedErr> {
+        <A as Argument>::parse(args)
+    }
+}
+
+macro_rules! arg_tuple {
+    ($($ty: ident),+) => {
+        impl<$($ty: Argument),+> Arguments for ($($ty,)+) {
+            const SCHEMAS: &'static [ArgumentSchema] = &[$($ty::SCHEMA),+];
+
+            fn parse<'a>(mut args: impl Iterator<Item = &'a str>) -> Result<Self, BoxedErr> {
+                Ok(($(<$ty as Argument>::parse(args.by_ref())?,)+))
+            }
+        }
+    };
+}
+
+arg_tuple!(A, B);
+arg_tuple!(A, B, C);
+arg_tuple!(A, B, C, D);
+
 macro_rules! arg_unit_enum {
     ($name: ident : $($string: literal => $var: ident),+$(,)?) => {
         impl Argument for $name {
+            const SCHEMA: ArgumentSchema = ArgumentSchema::new(ArgumentKind::Choice(&[$($string),+]));
+
             fn parse<'a>(mut args: impl Iterator<Item=&'a str>) -> Result<Self, BoxedErr> {
                 let arg = args.next().ok_or(
                     anyhow!("argument of type \"{}\" not supplied", stringify!($name))
@@ -60,8 +90,10 @@ macro_rules! arg_unit_enum {
 }
 
 macro_rules! arg_from_str {
-    ($($ty: ty)+) => { $(
+    ($($ty: ty => $schema: expr),+ $(,)?) => { $(
         impl Argument for $ty {
+            const SCHEMA: ArgumentSchema = $schema;
+
             fn parse<'a>(mut args: impl Iterator<Item=&'a str>) -> Result<Self, BoxedErr> {
                 let arg = args.next().ok_or(
                     anyhow!("argument of type \"{}\" not supplied", stringify!($ty))
@@ -72,7 +104,17 @@ macro_rules! arg_from_str {
     )+ };
 }
 
-impl<T: Argument + sealed::Sealed> Argument for Option<T> {
+impl Argument for () {
+    const SCHEMA: ArgumentSchema = ArgumentSchema { count: 0, ..ArgumentSchema::new(ArgumentKind::Nothing) };
+
+    fn parse<'a>(_: impl Iterator<Item=&'a str>) -> Result<Self, BoxedErr> {
+        Ok(())
+    }
+}
+
+impl<T: Argument> Argument for Option<T> {
+    const SCHEMA: ArgumentSchema = ArgumentSchema { optional: true, ..T::SCHEMA };
+
     fn parse<'a>(mut args: impl Iterator<Item=&'a str>) -> Result<Self, BoxedErr> {
         let Some(arg) = args.next() else {
             return Ok(None)
@@ -81,7 +123,9 @@ impl<T: Argument + sealed::Sealed> Argument for Option<T> {
     }
 }
 
-impl<T: Argument + sealed::Sealed> Argument for Vec<T> {
+impl<T: Argument> Argument for Vec<T> {
+    const SCHEMA: ArgumentSchema = ArgumentSchema { repeated: true, ..T::SCHEMA };
+
     fn parse<'a>(args: impl Iterator<Item=&'a str>) -> Result<Self, BoxedErr> {
         args.map(
             |arg| T::parse([arg].into_iter())
@@ -89,7 +133,9 @@ impl<T: Argument + sealed::Sealed> Argument for Vec<T> {
     }
 }
 
-impl<const N: usize, T: Argument + sealed::Sealed> Argument for [T; N] {
+impl<const N: usize, T: Argument> Argument for [T; N] {
+    const SCHEMA: ArgumentSchema = ArgumentSchema { count: N, ..T::SCHEMA };
+
     fn parse<'a>(args: impl Iterator<Item=&'a str>) -> Result<Self, BoxedErr> {
         // TODO: When https://github.com/rust-lang/rust/issues/89379 is stabilized, this can be optimized
         let args = args.take(N).map(
@@ -124,6 +170,22 @@ pub enum TilingDirection {
     Right, UpRight, Up, UpLeft, Left, DownLeft, Down, DownRight
 }
 
+/// A way to blend a sprite onto the sprites below it. Used in [`Variant::Blend`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum BlendMode {
+    /// Draws the sprite over the ones below it.
+    #[default]
+    Normal,
+    /// Adds the sprite's colors to the ones below it.
+    Add,
+    /// Multiplies the sprite's colors with the ones below it.
+    Multiply,
+    /// Inverts both colors, multiplies them, and inverts the result.
+    Screen,
+    /// Subtracts the sprite's colors from the ones below it.
+    Subtract
+}
+
 arg_unit_enum!{
     MetaKernel:
         "full" => Full,
@@ -143,6 +205,112 @@ arg_unit_enum!{
         "dr" => DownRight
 }
 
+arg_unit_enum!{
+    BlendMode:
+        "normal" => Normal,
+        "add" => Add,
+        "multiply" => Multiply,
+        "screen" => Screen,
+        "subtract" => Subtract
+}
+
+/// A palette to use for a scene, optionally from a specific world. Used in [`Flag::Palette`](super::Flag::Palette).
+///
+/// Written as `name`, or as `world/name` to pick a palette from a specific world.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PaletteName {
+    /// The world to take the palette from, if one was given.
+    pub world: Option<String>,
+    /// The name of the palette.
+    pub name: String
+}
+
+impl Argument for PaletteName {
+    const SCHEMA: ArgumentSchema = ArgumentSchema::new(ArgumentKind::String);
+
+    fn parse<'a>(mut args: impl Iterator<Item=&'a str>) -> Result<Self, BoxedErr> {
+        let first = args.next().ok_or(
+            anyhow!("argument of type \"PaletteName\" not supplied")
+        )?.to_string();
+        Ok( match args.next() {
+            Some(name) => Self { world: Some(first), name: name.to_string() },
+            None => Self { world: None, name: first }
+        } )
+    }
+}
+
+/// A tile to take a sprite from, optionally from a specific world. Used in [`Variant::Mask`](super::Variant::Mask).
+///
+/// Written as `name`, or as `world/name` to pick a tile from a specific world.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TileName {
+    /// The world to take the tile from, if one was given.
+    pub world: Option<String>,
+    /// The name of the tile.
+    pub name: String
+}
+
+impl Argument for TileName {
+    const SCHEMA: ArgumentSchema = ArgumentSchema::new(ArgumentKind::String);
+
+    fn parse<'a>(mut args: impl Iterator<Item=&'a str>) -> Result<Self, BoxedErr> {
+        let first = args.next().ok_or(
+            anyhow!("argument of type \"TileName\" not supplied")
+        )?.to_string();
+        Ok( match args.next() {
+            Some(name) => Self { world: Some(first), name: name.to_string() },
+            None => Self { world: None, name: first }
+        } )
+    }
+}
+
+/// A number that can change over the course of a scene. Used in numeric variant arguments.
+///
+/// Written as keyframes separated by `~`, like `0~360`.
+/// The keyframes are spread evenly from the first frame of the scene to the last,
+/// and the number is linearly interpolated between them.
+/// A single number stays the same for the whole scene.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframed(pub Vec<f32>);
+
+impl Keyframed {
+    /// Gets the value of this number partway through a scene,
+    /// from 0 on the first frame to 1 on the last.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn at(&self, progress: f32) -> f32 {
+        let Some(last) = self.0.len().checked_sub(1) else {
+            return 0.0
+        };
+        let position = progress.clamp(0.0, 1.0) * last as f32;
+        let index = (position.floor() as usize).min(last);
+        let (start, end) = (self.0[index], self.0[(index + 1).min(last)]);
+        start + (end - start) * (position - index as f32)
+    }
+}
+
+impl From<f32> for Keyframed {
+    fn from(value: f32) -> Self {
+        Self(vec![value])
+    }
+}
+
+impl Argument for Keyframed {
+    const SCHEMA: ArgumentSchema = ArgumentSchema::new(ArgumentKind::Keyframed);
+
+    fn parse<'a>(mut args: impl Iterator<Item=&'a str>) -> Result<Self, BoxedErr> {
+        let arg = args.next().ok_or(
+            anyhow!("argument of type \"Keyframed\" not supplied")
+        )?;
+        Ok(Self(arg.split('~').map(f32::from_str).collect::<Result<_, _>>()?))
+    }
+}
+
 arg_from_str! {
-    u8 i8 f32 isize Color String
+    u8 => ArgumentSchema::ranged(ArgumentKind::Integer, 0.0, 255.0),
+    i8 => ArgumentSchema::ranged(ArgumentKind::Integer, -128.0, 127.0),
+    f32 => ArgumentSchema::new(ArgumentKind::Float),
+    isize => ArgumentSchema::new(ArgumentKind::Integer),
+    Color => ArgumentSchema::new(ArgumentKind::Color),
+    String => ArgumentSchema::new(ArgumentKind::String)
 }
@@ -0,0 +1,227 @@
+//! Handles variants that are registered at runtime, outside of Chilly.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use displaydoc::Display;
+#[cfg(feature = "rendering")]
+use image::RgbaImage;
+use thiserror::Error;
+
+use super::args::BoxedErr;
+use super::schema::validate_all;
+use super::{Arguments, ArgumentSchema, Variant, VariantName};
+
+/// Runtime-accessible data about a custom variant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomVariantData {
+    /// The canonical name.
+    pub name: String,
+    /// A list of the aliases, including the name.
+    pub aliases: Vec<String>,
+    /// A description of what this does.
+    pub description: String,
+    /// Descriptions of the arguments that this takes, in order.
+    pub arguments: &'static [ArgumentSchema]
+}
+
+/// Something went wrong while registering a custom variant.
+#[derive(Debug, Clone, PartialEq, Eq, Display, Error)]
+pub enum RegistryError {
+    /// the name or alias "{0}" is already used by another variant
+    NameTaken(String)
+}
+
+/// The arguments of a custom variant, with their type erased.
+trait ErasedArgument: Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn eq_erased(&self, other: &dyn ErasedArgument) -> bool;
+}
+
+impl<A: Debug + PartialEq + Send + Sync + 'static> ErasedArgument for A {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn eq_erased(&self, other: &dyn ErasedArgument) -> bool {
+        other.as_any().downcast_ref::<A>().is_some_and(|other| self == other)
+    }
+}
+
+type ErasedParser = dyn for<'a> Fn(&mut dyn Iterator<Item = &'a str>) -> Result<Arc<dyn ErasedArgument>, BoxedErr>
+    + Send + Sync;
+#[cfg(feature = "rendering")]
+type ErasedTransform = dyn Fn(&dyn Any, &mut RgbaImage) -> Result<(), String> + Send + Sync;
+
+/// Everything needed to parse and render a custom variant.
+struct Definition {
+    data: CustomVariantData,
+    parse: Box<ErasedParser>,
+    #[cfg(feature = "rendering")]
+    transform: Option<Box<ErasedTransform>>
+}
+
+/// A parsed custom variant, along with its arguments.
+#[derive(Clone)]
+pub struct CustomVariant {
+    definition: Arc<Definition>,
+    arguments: Arc<dyn ErasedArgument>
+}
+
+impl CustomVariant {
+    /// Gets the data of the variant this was parsed as.
+    #[must_use]
+    pub fn data(&self) -> &CustomVariantData {
+        &self.definition.data
+    }
+
+    /// Gets the arguments this was parsed with,
+    /// if they're of the type the variant was registered with.
+    #[must_use]
+    pub fn arguments<A: 'static>(&self) -> Option<&A> {
+        self.arguments.as_any().downcast_ref()
+    }
+
+    /// Applies this variant's transform to a sprite, if it has one.
+    #[cfg(feature = "rendering")]
+    pub(crate) fn apply(&self, image: &mut RgbaImage) -> Result<(), String> {
+        match &self.definition.transform {
+            Some(transform) => transform(self.arguments.as_any(), image),
+            None => Ok(())
+        }
+    }
+}
+
+impl Debug for CustomVariant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple(&self.definition.data.name).field(&self.arguments).finish()
+    }
+}
+
+impl PartialEq for CustomVariant {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.definition, &other.definition)
+            && self.arguments.eq_erased(other.arguments.as_ref())
+    }
+}
+
+/// A set of variants that aren't built into Chilly,
+/// which can be parsed alongside the built-in ones.
+///
+/// Pass this to [`parse_with_variants`](crate::parser::parse_with_variants) to use it.
+#[derive(Default)]
+pub struct VariantRegistry {
+    definitions: Vec<Arc<Definition>>,
+    aliases: HashMap<String, Arc<Definition>>
+}
+
+impl VariantRegistry {
+    /// Creates an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a variant that takes an argument of the given type,
+    /// or a tuple of them, and that doesn't change how sprites render.
+    ///
+    /// Its name is always an alias of it.
+    ///
+    /// # Errors
+    /// Errors if the name or any alias is already used by another variant, including built-in ones.
+    pub fn register<A>(&mut self, name: &str, aliases: &[&str], description: &str) -> Result<(), RegistryError>
+    where A: Arguments + Debug + PartialEq + Send + Sync + 'static {
+        self.insert::<A>(name, aliases, description, |definition| definition)
+    }
+
+    /// Registers a variant that takes an argument of the given type,
+    /// or a tuple of them, and transforms the sprite of any tile it's applied to.
+    ///
+    /// If the transform returns an error, the scene fails to render with it.
+    ///
+    /// # Errors
+    /// Errors if the name or any alias is already used by another variant, including built-in ones.
+    #[cfg(feature = "rendering")]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn register_with_transform<A, F>(
+        &mut self, name: &str, aliases: &[&str], description: &str, transform: F
+    ) -> Result<(), RegistryError>
+    where
+        A: Arguments + Debug + PartialEq + Send + Sync + 'static,
+        F: Fn(&A, &mut RgbaImage) -> Result<(), String> + Send + Sync + 'static
+    {
+        self.insert::<A>(name, aliases, description, |mut definition| {
+            definition.transform = Some(Box::new(move |arguments, image| transform(
+                arguments.downcast_ref().expect("custom variant arguments should be of the registered type"),
+                image
+            )));
+            definition
+        })
+    }
+
+    fn insert<A>(
+        &mut self, name: &str, aliases: &[&str], description: &str,
+        finish: impl FnOnce(Definition) -> Definition
+    ) -> Result<(), RegistryError>
+    where A: Arguments + Debug + PartialEq + Send + Sync + 'static {
+        let mut all_aliases = vec![name.to_string()];
+        all_aliases.extend(aliases.iter().map(ToString::to_string).filter(|alias| alias != name));
+        for alias in &all_aliases {
+            if VariantName::from_alias(alias).is_some()
+                || Variant::collapse_alias(alias).is_some()
+                || self.aliases.contains_key(alias)
+            {
+                return Err(RegistryError::NameTaken(alias.clone()));
+            }
+        }
+        let definition = Arc::new(finish(Definition {
+            data: CustomVariantData {
+                name: name.to_string(),
+                aliases: all_aliases.clone(),
+                description: description.to_string(),
+                arguments: A::SCHEMAS
+            },
+            parse: Box::new(|arguments| Ok(Arc::new(A::parse(arguments)?))),
+            #[cfg(feature = "rendering")]
+            transform: None
+        }));
+        for alias in all_aliases {
+            self.aliases.insert(alias, Arc::clone(&definition));
+        }
+        self.definitions.push(definition);
+        Ok(())
+    }
+
+    /// Lists the data of every registered variant, in the order they were registered.
+    pub fn variants(&self) -> impl Iterator<Item = &CustomVariantData> {
+        self.definitions.iter().map(|definition| &definition.data)
+    }
+
+    /// Parses a custom variant from one of its aliases and a list of arguments.
+    ///
+    /// Returns `None` if no variant has the alias.
+    ///
+    /// # Errors
+    /// Errors if the arguments fail to parse, or are outside the bounds given in their schema.
+    pub fn parse<'a>(
+        &self, alias: &str, arguments: impl Iterator<Item = &'a str>
+    ) -> Option<Result<Variant, BoxedErr>> {
+        let definition = self.aliases.get(alias)?;
+        let arguments = arguments.collect::<Vec<_>>();
+        if let Err((_, err)) = validate_all(definition.data.arguments, &arguments) {
+            return Some(Err(err));
+        }
+        Some((definition.parse)(&mut arguments.into_iter()).map(|arguments| Variant::Custom(CustomVariant {
+            definition: Arc::clone(definition),
+            arguments
+        })))
+    }
+}
+
+impl Debug for VariantRegistry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.variants()).finish()
+    }
+}
@@ -3,7 +3,7 @@
 use paste::paste;
 use crate::database::structures::Color;
 
-use super::{RuntimeData, ArgumentError, args::Argument, arg_macro};
+use super::{RuntimeData, ArgumentError, args::{Argument, PaletteName}, arg_macro};
 use std::fmt::Formatter;
 
 
@@ -32,8 +32,9 @@ arg_macro! {
         {
             Palette,
             ["p", "pal", "palette"],
-            "Specifies a palette to use for a scene.",
-            [String]
+            "Specifies a palette to use for a scene.\n\
+             A palette from a specific world can be chosen with `world/palette`.",
+            [PaletteName]
         },
         {
             NoLoop,
@@ -4,11 +4,15 @@ mod flags;
 mod variants;
 mod args;
 mod argmacro;
+mod custom;
+mod schema;
 
 pub use flags::*;
 use thiserror::Error;
 pub use variants::*;
 pub use args::*;
+pub use custom::{CustomVariant, CustomVariantData, RegistryError, VariantRegistry};
+pub use schema::{ArgumentKind, ArgumentSchema};
 pub(crate) use argmacro::arg_macro;
 
 use displaydoc::Display;
@@ -23,8 +27,22 @@ pub struct RuntimeData<NAME> {
     pub aliases: &'static [&'static str],
     /// A description of what this does.
     pub description: &'static str,
-    /// A list of types that this takes, as strings.
-    pub arguments: &'static [&'static str]
+    /// A description of each argument that this takes, in order.
+    pub arguments: &'static [ArgumentSchema]
+}
+
+impl<NAME> RuntimeData<NAME> {
+    /// Writes out how to use this, like `meta/[integer, default 1]/...`,
+    /// with arguments separated by slashes as they would be in a scene.
+    #[must_use]
+    pub fn usage(&self) -> String {
+        let mut usage = self.aliases[0].to_string();
+        for argument in self.arguments.iter().filter(|argument| argument.count != 0) {
+            usage.push('/');
+            usage.push_str(&argument.to_string());
+        }
+        usage
+    }
 }
 
 /// Something went wrong while parsing an argument.
@@ -0,0 +1,158 @@
+//! Holds structured descriptions of the arguments that flags and variants take.
+
+use std::fmt::{Display, Formatter};
+use anyhow::anyhow;
+
+use super::args::BoxedErr;
+
+/// The kind of value that an argument takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArgumentKind {
+    /// Takes no value at all.
+    Nothing,
+    /// A whole number.
+    Integer,
+    /// A number that may be fractional.
+    Float,
+    /// A number that may be fractional, and may change over the course of a scene.
+    /// See [`Keyframed`](super::Keyframed).
+    Keyframed,
+    /// A palette index, a color name, or an RGB color.
+    Color,
+    /// Any text.
+    String,
+    /// One of a fixed set of choices.
+    Choice(&'static [&'static str]),
+    /// A value of a type that Chilly doesn't know about.
+    Other
+}
+
+/// A description of a single argument that a flag or variant takes.
+///
+/// This is enough to validate the argument before it's parsed,
+/// and to describe it to a user, like in help text or a slash command's options.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ArgumentSchema {
+    /// The kind of value this takes.
+    pub kind: ArgumentKind,
+    /// Whether this can be left out.
+    pub optional: bool,
+    /// Whether this takes any number of values, rather than [`count`](ArgumentSchema::count).
+    pub repeated: bool,
+    /// How many values this takes.
+    pub count: usize,
+    /// The smallest number this can be, if it's numeric.
+    pub min: Option<f64>,
+    /// A number that this must be greater than, if it's numeric.
+    ///
+    /// Unlike [`min`](ArgumentSchema::min), the number itself is out of bounds.
+    pub above: Option<f64>,
+    /// The largest number this can be, if it's numeric.
+    pub max: Option<f64>,
+    /// The value this takes if it's left out, written as it would be in a scene.
+    pub default: Option<&'static str>
+}
+
+impl ArgumentSchema {
+    /// Creates a schema for a single, required value of a kind.
+    #[must_use]
+    pub const fn new(kind: ArgumentKind) -> Self {
+        Self { kind, optional: false, repeated: false, count: 1, min: None, above: None, max: None, default: None }
+    }
+
+    /// Creates a schema for a single, required number between two bounds.
+    #[must_use]
+    pub const fn ranged(kind: ArgumentKind, min: f64, max: f64) -> Self {
+        Self { min: Some(min), max: Some(max), ..Self::new(kind) }
+    }
+
+    /// Checks a single value against this schema's bounds.
+    ///
+    /// Values that aren't numbers are left for the argument's parser to reject.
+    /// Keyframed values are checked at every keyframe.
+    ///
+    /// # Errors
+    /// Errors if the value is out of bounds, or is infinite or NaN.
+    pub fn validate(&self, value: &str) -> Result<(), BoxedErr> {
+        if !matches!(self.kind, ArgumentKind::Integer | ArgumentKind::Float | ArgumentKind::Keyframed) {
+            return Ok(());
+        }
+        // Parsed at the precision the renderer uses, so values too large for it are caught too
+        for number in value.split('~').filter_map(|number| number.parse::<f32>().ok()) {
+            if !number.is_finite() {
+                return Err(anyhow!("must be a finite number, but was {number}").into());
+            }
+            let number = f64::from(number);
+            match (self.min, self.above, self.max) {
+                (Some(min), _, Some(max)) if !(min ..= max).contains(&number) =>
+                    return Err(anyhow!("must be between {min} and {max}, but was {number}").into()),
+                (Some(min), _, None) if number < min =>
+                    return Err(anyhow!("must be at least {min}, but was {number}").into()),
+                (None, Some(above), Some(max)) if number <= above || number > max =>
+                    return Err(anyhow!("must be over {above} and at most {max}, but was {number}").into()),
+                (None, Some(above), None) if number <= above =>
+                    return Err(anyhow!("must be over {above}, but was {number}").into()),
+                (None, None, Some(max)) if number > max =>
+                    return Err(anyhow!("must be at most {max}, but was {number}").into()),
+                _ => ()
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks a list of arguments against the schemas they're meant to match, in order.
+///
+/// Each schema checks as many values as its [`count`](ArgumentSchema::count),
+/// or every value that's left if it's repeated.
+pub(crate) fn validate_all(schemas: &[ArgumentSchema], arguments: &[&str]) -> Result<(), (usize, BoxedErr)> {
+    let mut arguments = arguments.iter().enumerate();
+    for schema in schemas {
+        let count = if schema.repeated { usize::MAX } else { schema.count };
+        for (index, argument) in arguments.by_ref().take(count) {
+            schema.validate(argument).map_err(|err| (index, err))?;
+        }
+    }
+    Ok(())
+}
+
+impl Display for ArgumentKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArgumentKind::Nothing => Ok(()),
+            ArgumentKind::Integer => write!(f, "integer"),
+            ArgumentKind::Float => write!(f, "number"),
+            ArgumentKind::Keyframed => write!(f, "keyframes"),
+            ArgumentKind::Color => write!(f, "color"),
+            ArgumentKind::String => write!(f, "text"),
+            ArgumentKind::Choice(choices) => write!(f, "{}", choices.join("|")),
+            ArgumentKind::Other => write!(f, "value")
+        }
+    }
+}
+
+/// Writes the schema like `<integer, 1 to 255>`, or `[number, default 100]` if it's optional.
+impl Display for ArgumentSchema {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (open, close) = if self.optional { ('[', ']') } else { ('<', '>') };
+        write!(f, "{open}{}", self.kind)?;
+        match (self.min, self.above, self.max) {
+            (Some(min), _, Some(max)) => write!(f, ", {min} to {max}")?,
+            (Some(min), _, None) => write!(f, ", at least {min}")?,
+            (None, Some(above), Some(max)) => write!(f, ", over {above} up to {max}")?,
+            (None, Some(above), None) => write!(f, ", over {above}")?,
+            (None, None, Some(max)) => write!(f, ", at most {max}")?,
+            (None, None, None) => ()
+        }
+        if let Some(default) = self.default {
+            write!(f, ", default {default}")?;
+        }
+        write!(f, "{close}")?;
+        if self.repeated {
+            write!(f, "...")?;
+        } else if self.count != 1 {
+            write!(f, " x{}", self.count)?;
+        }
+        Ok(())
+    }
+}
@@ -3,11 +3,14 @@
 use std::fmt::Formatter;
 use paste::paste;
 pub use super::args::{
+    BlendMode,
+    Keyframed,
     MetaKernel,
     Argument,
+    TileName,
     TilingDirection
 };
-use super::{RuntimeData, ArgumentError, arg_macro};
+use super::{RuntimeData, ArgumentError, CustomVariant, arg_macro};
 use crate::database::structures::Color;
 use std::str::FromStr;
 
@@ -20,8 +23,26 @@ arg_macro! {
             ["meta", "m"],
            "Adds an outline to a tile's sprite.\n\
             Optionally, it can be specified how many times to outline, \
-            an outline kernel to use, and an outline size.",
-            [Option<i8>, Option<MetaKernel>, Option<u8>]
+            an outline kernel to use, an outline size, and an outline color.",
+            [
+                Option<i8> {min: -8.0, max: 8.0, default: "1"},
+                Option<MetaKernel> {default: "full"},
+                Option<u8> {min: 1.0, max: 8.0, default: "1"},
+                Option<Color>
+            ]
+        },
+        {
+            Shadow,
+            ["shadow", "ds"],
+            "Adds a drop shadow beneath a tile's sprite.\n\
+             Optionally, it can be specified how far to offset the shadow horizontally and vertically, \
+             the shadow's color, and how far the shadow spreads out.",
+            [
+                Option<isize> {min: -64.0, max: 64.0, default: "1"},
+                Option<isize> {min: -64.0, max: 64.0, default: "1"},
+                Option<Color> {default: "0,4"},
+                Option<u8> {max: 16.0, default: "0"}
+            ]
         },
         {
             Noop,
@@ -85,6 +106,175 @@ arg_macro! {
              This variant is aliased, so specifying the variant's name is optional.",
             [Color]
         },
+        {
+            Gradient,
+            ["grad", "gradient"],
+            "Tints the tile with a gradient between two colors.\n\
+             Optionally, an angle in degrees can be specified. \
+             An angle of 0 goes from left to right, and 90 goes from top to bottom.",
+            [Color, Color, Option<Keyframed> {default: "0"}]
+        },
+        {
+            HorizontalGradient,
+            ["hgrad", "hgradient"],
+            "Tints the tile with a gradient between two colors, from left to right.",
+            [Color, Color]
+        },
+        {
+            VerticalGradient,
+            ["vgrad", "vgradient"],
+            "Tints the tile with a gradient between two colors, from top to bottom.",
+            [Color, Color]
+        },
+        {
+            Stripes,
+            ["stripes", "flag"],
+            "Tints the tile with rows of colors, from top to bottom, like a flag.",
+            [Vec<Color>]
+        },
+        {
+            Inactive,
+            ["in", "inactive"],
+            "Gives the tile its inactive color, like text that isn't part of a rule.\n\
+             Does nothing if the tile has no inactive color.",
+            []
+        },
+        {
+            FlipX,
+            ["flipx", "fx"],
+            "Flips the tile's sprite horizontally.",
+            []
+        },
+        {
+            FlipY,
+            ["flipy", "fy"],
+            "Flips the tile's sprite vertically.",
+            []
+        },
+        {
+            Rotate,
+            ["rot", "rotate"],
+            "Rotates the tile's sprite clockwise by a number of degrees.\n\
+             The sprite's bounds grow to fit the rotated sprite.",
+            [Keyframed]
+        },
+        {
+            Scale,
+            ["scale", "sc"],
+            "Scales the tile's sprite by a multiplier, which may be fractional.\n\
+             Multiple scales multiply together, up to the same limit.",
+            [Keyframed {above: 0.0, max: 16.0}]
+        },
+        {
+            Hue,
+            ["hue", "hueshift"],
+            "Shifts the hue of the tile's colors by a number of degrees.",
+            [Keyframed]
+        },
+        {
+            Invert,
+            ["inv", "invert"],
+            "Inverts the tile's colors.",
+            []
+        },
+        {
+            Grayscale,
+            ["gs", "grayscale", "greyscale"],
+            "Removes the color from the tile.\n\
+             Optionally, a percentage of the color to remove can be specified.",
+            [Option<Keyframed> {min: 0.0, max: 100.0, default: "100"}]
+        },
+        {
+            Brightness,
+            ["bright", "brightness"],
+            "Scales the brightness of the tile's colors by a percentage.",
+            [Keyframed {min: 0.0}]
+        },
+        {
+            Contrast,
+            ["contrast"],
+            "Scales the contrast of the tile's colors by a percentage.",
+            [Keyframed {min: 0.0}]
+        },
+        {
+            Saturation,
+            ["sat", "saturation"],
+            "Scales the saturation of the tile's colors by a percentage.",
+            [Keyframed {min: 0.0}]
+        },
+        {
+            Opacity,
+            ["op", "opacity", "alpha"],
+            "Sets the opacity of the tile as a percentage.",
+            [Keyframed {min: 0.0, max: 100.0}]
+        },
+        {
+            Blend,
+            ["blend", "b"],
+            "Sets how the tile is blended onto the tiles below it.\n\
+             May be one of normal, add, multiply, screen, or subtract.",
+            [BlendMode]
+        },
+        {
+            Wave,
+            ["wave", "wx"],
+            "Displaces each row of the tile's sprite sideways along a wave.\n\
+             Takes the amplitude and the wavelength of the wave, in pixels.",
+            [Keyframed {min: -64.0, max: 64.0}, Keyframed {above: 0.0, max: 1024.0}]
+        },
+        {
+            VerticalWave,
+            ["vwave", "wy"],
+            "Displaces each column of the tile's sprite up and down along a wave.\n\
+             Takes the amplitude and the wavelength of the wave, in pixels.",
+            [Keyframed {min: -64.0, max: 64.0}, Keyframed {above: 0.0, max: 1024.0}]
+        },
+        {
+            Pixelate,
+            ["pixelate", "pix"],
+            "Pixelates the tile's sprite into square blocks of a given size.",
+            [u8 {min: 1.0}]
+        },
+        {
+            Blur,
+            ["blur"],
+            "Blurs the tile's sprite with a gaussian blur of a given strength.",
+            [Keyframed {above: 0.0, max: 32.0}]
+        },
+        {
+            BoxBlur,
+            ["boxblur", "bblur"],
+            "Blurs the tile's sprite by averaging each pixel within a given radius.",
+            [u8]
+        },
+        {
+            Noise,
+            ["noise"],
+            "Adds random noise to the tile's colors, with a strength as a percentage.\n\
+             The noise is the same every time the tile is rendered in the same place.",
+            [Keyframed {min: 0.0, max: 100.0}]
+        },
+        {
+            Crop,
+            ["crop"],
+            "Crops the tile's sprite to a rectangle.\n\
+             Takes the left and top edges of the rectangle, then its width and height, in pixels.",
+            [u8, u8, u8, u8]
+        },
+        {
+            Pad,
+            ["pad"],
+            "Adds empty space around the tile's sprite.\n\
+             Takes the space to add on the left, top, right, and bottom, in pixels.",
+            [u8, u8, u8, u8]
+        },
+        {
+            Mask,
+            ["mask"],
+            "Only keeps the parts of the tile's sprite that overlap with another tile's sprite.\n\
+             The other tile can be written as `world/name` to take it from a specific world.",
+            [TileName]
+        },
         {
             Displace,
             ["disp", "displace"],
@@ -98,5 +288,12 @@ arg_macro! {
             (color_name if Color::from_str(color_name).is_ok()) =>
                 Variant::Color(Color::from_str(color_name).expect("we checked that this works"))
         }
+    ],
+    extra: [
+        {
+            Custom,
+            "A variant registered at runtime in a [`VariantRegistry`](super::VariantRegistry).",
+            CustomVariant
+        }
     ]
 }
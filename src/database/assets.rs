@@ -2,19 +2,20 @@
 //! Handles loading and scraping of external assets into the database.
 
 use std::{
-    collections::{BTreeSet, HashMap},
-    fs,
-    io::{self, Read},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    mem,
+    io,
     path::{Path, PathBuf},
     str::FromStr
 };
 use displaydoc::Display;
-use itertools::Itertools;
 use regex_lite::Regex;
 use thiserror::Error;
-use crate::database::Database;
+use crate::database::{precedence_key, source::{AssetSource, DirectorySource}, Database, SpriteFiles, TileConflict};
 
-use super::structures::{Color, TileData, Tiling};
+use super::structures::{Color, TileData, Tiling, World};
+#[cfg(feature = "rendering")]
+use super::structures::{Palette, PALETTE_SIZE};
 
 // Taken from once_cell docs
 macro_rules! regex {
@@ -38,61 +39,344 @@ pub enum LoadError {
     LuaDataNotFound(&'static str, PathBuf),
     /// A lua file was invalid: {0}
     InvalidLua(&'static str),
+    #[cfg(feature = "rendering")]
+    #[displaydoc("Failed to decode image at {0}: {1}")]
+    /// Failed to decode an image
+    ImageError(PathBuf, image::ImageError),
+    #[displaydoc("Palette at {0} is {1}x{2}, but palettes must be 7x5")]
+    /// A palette had the wrong dimensions
+    InvalidPalette(PathBuf, u32, u32),
 }
 
 impl Database {
     /// Load custom assets from a directory of worlds.
     ///
+    /// See [`Database::load_custom_from`] for details.
+    ///
     /// # Errors
     /// Bails if the path can't be read, or there's an issue reading the tile data.
-    pub fn load_custom(&mut self, path: impl AsRef<Path>) -> Result<(), LoadError> {
-        fs::read_dir(path)?
-            // Get the paths
-            .map(|c| Ok(c?.path()))
-            // Filter to only the directories
-            .filter_ok(|path| path.is_dir())
-            // Filter out the general assets directory
-            .filter_ok(|path| path.file_name().is_some_and(|str| str.to_str() != Some("general")))
-            // Load each directory
-            .try_for_each(|res| res.and_then(|path| self.load_custom_path(path)))
+    pub fn load_custom(&mut self, path: impl AsRef<Path>) -> Result<Vec<TileConflict>, LoadError> {
+        self.load_custom_from(&DirectorySource::new(path.as_ref()))
+    }
+
+    /// Load custom assets from an asset source, where each top-level directory is a world.
+    ///
+    /// If the `rendering` feature is enabled, each world's palettes are loaded from its `palettes` directory.
+    ///
+    /// Worlds are loaded in order of [`Database::world_precedence`],
+    /// so the result doesn't depend on the order the source lists them in.
+    ///
+    /// Returns a report of every tile that was defined by more than one world.
+    ///
+    /// # Errors
+    /// Bails if the source can't be read, or there's an issue reading the tile data.
+    pub fn load_custom_from(&mut self, source: &dyn AssetSource) -> Result<Vec<TileConflict>, LoadError> {
+        let mut worlds = Database::world_paths(source)?;
+        // Load the metadata first, since it decides the load order
+        for world in &worlds {
+            self.load_world_metadata(source, world)?;
+        }
+        worlds.sort_by_cached_key(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let (vanilla, index, priority, _) = precedence_key(&self.world_precedence, &self.worlds, &name);
+            (vanilla, index, priority, name)
+        });
+        // Load each directory
+        let mut loaded = HashSet::new();
+        for world in worlds {
+            loaded.extend(self.load_world_tiles(source, &world)?);
+        }
+        self.rebuild_aliases();
+        let mut conflicts = self.conflicts();
+        conflicts.retain(|conflict| loaded.contains(&conflict.name));
+        Ok(conflicts)
+    }
+
+    /// Lists the paths of every world in an asset source.
+    pub(crate) fn world_paths(source: &dyn AssetSource) -> Result<Vec<PathBuf>, LoadError> {
+        Ok(
+            source.read_dir(Path::new(""))?
+                .into_iter()
+                // Filter to only the directories
+                .filter(|path| source.is_dir(path))
+                // Filter out the general assets directory
+                .filter(|path| path.file_name().is_some_and(|str| str.to_str() != Some("general")))
+                .collect()
+        )
+    }
+
+    /// Loads custom assets from a single directory of an asset source.
+    ///
+    /// Returns the names of the tiles that were loaded.
+    ///
+    /// # Errors
+    /// Bails if the path can't be read, or there's an issue reading the world's data.
+    pub(crate) fn load_custom_path(&mut self, source: &dyn AssetSource, path: &Path) -> Result<Vec<String>, LoadError> {
+        self.load_world_metadata(source, path)?;
+        self.load_world_tiles(source, path)
+    }
+
+    /// Loads a world's `world.toml`, falling back to the default metadata if it doesn't have one.
+    ///
+    /// # Errors
+    /// Bails if the file can't be read, or isn't valid.
+    fn load_world_metadata(&mut self, source: &dyn AssetSource, path: &Path) -> Result<(), LoadError> {
+        let name = Database::world_name(path)?;
+        let mut world: World = match source.read_to_string(&path.join("world.toml")) {
+            Ok(file_buf) => toml::from_str(&file_buf)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => World::default(),
+            Err(err) => return Err(err.into())
+        };
+        world.name.clone_from(&name);
+        self.worlds.insert(name, world);
+        Ok(())
     }
 
-    /// Loads custom assets from a single directory.
+    /// Gets the name of a world from its path.
+    fn world_name(path: &Path) -> Result<String, LoadError> {
+        Ok(
+            path.file_name()
+                .ok_or(
+                    LoadError::IoError(io::Error::other("no world name found"))
+                )?
+                .to_string_lossy()
+                .to_string()
+        )
+    }
+
+    /// Loads the tiles and palettes of a world.
+    ///
+    /// Returns the names of the tiles that were loaded.
     ///
     /// # Errors
     /// Bails if the path can't be read, or there's an issue reading the tile data.
-    fn load_custom_path(&mut self, path: impl AsRef<Path>) -> Result<(), LoadError> {
+    fn load_world_tiles(&mut self, source: &dyn AssetSource, path: &Path) -> Result<Vec<String>, LoadError> {
+        let dir_name = Database::world_name(path)?;
         // Read the sprites file
-        let dir_name = path.as_ref()
-            .file_name()
-            .ok_or(
-                LoadError::IoError(io::Error::other("no world name found"))
-            )?
-            .to_string_lossy()
-            .to_string();
-        let sprites_path = path.as_ref().join("sprites.toml");
-        let mut file_buf = String::new();
-        let mut f = fs::File::open(sprites_path)?;
-        f.read_to_string(&mut file_buf)?;
-        drop(f); // Close immediately
+        let file_buf = source.read_to_string(&path.join("sprites.toml"))?;
         // Deserialize
         let data: HashMap<String, TileData> = toml::from_str(&file_buf)?;
-        self.tiles.extend(
+        let names = data.keys().cloned().collect();
+        for (name, mut tile) in data {
             // Set the world of the tile
-            data.into_iter().update(
-              |(_, t)| t.directory = dir_name.clone()
-            )
+            tile.directory.clone_from(&dir_name);
+            self.insert_custom_tile(name, tile);
+        }
+        self.scan_sprites(source, &path.join("sprites"), &dir_name)?;
+        #[cfg(feature = "rendering")]
+        self.load_palettes(source, &path.join("palettes"), &dir_name)?;
+        Ok(names)
+    }
+
+    /// Loads every palette in a directory of an asset source as part of a world.
+    ///
+    /// Does nothing if the directory doesn't exist.
+    ///
+    /// # Errors
+    /// Bails if a palette can't be read, or has the wrong dimensions.
+    #[cfg(feature = "rendering")]
+    fn load_palettes(&mut self, asset_source: &dyn AssetSource, path: &Path, world: &str) -> Result<(), LoadError> {
+        let entries = match asset_source.read_dir(path) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into())
+        };
+        for source in entries {
+            if source.extension().and_then(|ext| ext.to_str()) != Some("png") {
+                continue;
+            }
+            let Some(name) = source.file_stem().map(|name| name.to_string_lossy().to_string()) else {
+                continue;
+            };
+            let image = image::load_from_memory(&asset_source.read(&source)?)
+                .map_err(|err| LoadError::ImageError(source.clone(), err))?
+                .into_rgba8();
+            if image.dimensions() != PALETTE_SIZE {
+                return Err(LoadError::InvalidPalette(source, image.width(), image.height()));
+            }
+            self.insert_palette(Palette { name, world: world.to_string(), source, image });
+        }
+        Ok(())
+    }
+
+    /// Inserts a palette, replacing the one of the same name from the same world
+    /// and keeping palettes of the same name in order of world precedence.
+    #[cfg(feature = "rendering")]
+    fn insert_palette(&mut self, palette: Palette) {
+        let (precedence, worlds) = (&self.world_precedence, &self.worlds);
+        let palettes = self.palettes.entry(palette.name.clone()).or_default();
+        palettes.retain(|existing| existing.world != palette.world);
+        palettes.push(palette);
+        palettes.sort_by(|a, b|
+            precedence_key(precedence, worlds, &a.world).cmp(&precedence_key(precedence, worlds, &b.world))
         );
+    }
+
+    /// Adds a world's sprite files to the sprite index,
+    /// and fills in any missing sprite metadata for its tiles.
+    ///
+    /// Sprite files are named `{sprite}_{frame}_{wobble}.png`.
+    /// Does nothing if the directory doesn't exist.
+    ///
+    /// # Errors
+    /// Bails if the directory or a sprite can't be read.
+    fn scan_sprites(&mut self, source: &dyn AssetSource, path: &Path, world: &str) -> Result<(), LoadError> {
+        let entries = match source.read_dir(path) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into())
+        };
+        // Collect the files of each frame and wobble that exist for each sprite
+        let mut sprites: BTreeMap<String, SpriteFiles> = BTreeMap::new();
+        for entry in entries {
+            let Some((sprite, frame, wobble)) = entry.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(parse_sprite_name)
+            else {
+                continue;
+            };
+            sprites.entry(sprite.to_string()).or_default().insert((frame, wobble), entry.clone());
+        }
+        let tiles = self.tiles.values_mut()
+            .chain(self.overridden.values_mut().flatten())
+            .filter(|tile| tile.directory == world);
+        for tile in tiles {
+            let Some(files) = sprites.get(&tile.sprite) else {
+                continue;
+            };
+            if tile.sprite_size.is_none() {
+                // Measure the first frame, as that's the one that's always there
+                if let Some((_, first)) = files.first_key_value() {
+                    tile.sprite_size = png_size(&source.read(first)?);
+                }
+            }
+            if tile.frame_count.is_none() {
+                tile.frame_count = frame_count(files, tile.tiling);
+            }
+            if tile.wobble_count.is_none() {
+                let wobbles = files.keys().map(|(_, wobble)| wobble).collect::<BTreeSet<_>>();
+                tile.wobble_count = u8::try_from(wobbles.len()).ok();
+            }
+        }
+        self.sprite_index.insert(world.to_string(), sprites);
         Ok(())
     }
 
+    /// Inserts a tile from any world, vanilla included, respecting world precedence.
+    ///
+    /// If a tile from the same world already exists, it's replaced.
+    fn insert_custom_tile(&mut self, name: String, tile: TileData) {
+        let Some(existing) = self.tiles.get_mut(&name) else {
+            self.tiles.insert(name, tile);
+            return;
+        };
+        if existing.directory == tile.directory {
+            *existing = tile;
+            return;
+        }
+        let (precedence, worlds) = (&self.world_precedence, &self.worlds);
+        let shadowed = if precedence_key(precedence, worlds, &tile.directory) < precedence_key(precedence, worlds, &existing.directory) {
+            mem::replace(existing, tile)
+        } else {
+            tile
+        };
+        let shadowed_list = self.overridden.entry(name).or_default();
+        shadowed_list.retain(|tile| tile.directory != shadowed.directory);
+        shadowed_list.push(shadowed);
+        shadowed_list.sort_by(|a, b|
+            precedence_key(precedence, worlds, &a.directory).cmp(&precedence_key(precedence, worlds, &b.directory))
+        );
+    }
+
+    /// Lists the names of every tile that a world defines, including overridden ones.
+    pub(crate) fn world_tile_names(&self, world: &str) -> BTreeSet<String> {
+        let current = self.tiles.iter()
+            .filter(|(_, tile)| tile.directory == world)
+            .map(|(name, _)| name.clone());
+        let shadowed = self.overridden.iter()
+            .filter(|(_, shadowed)| shadowed.iter().any(|tile| tile.directory == world))
+            .map(|(name, _)| name.clone());
+        current.chain(shadowed).collect()
+    }
+
+    /// Removes every tile and palette belonging to a world, along with its metadata,
+    /// putting back any tiles that it was overriding.
+    pub(crate) fn remove_world(&mut self, world: &str) {
+        self.worlds.remove(world);
+        self.sprite_index.remove(world);
+        #[cfg(feature = "rendering")]
+        {
+            for palettes in self.palettes.values_mut() {
+                palettes.retain(|palette| palette.world != world);
+            }
+            self.palettes.retain(|_, palettes| !palettes.is_empty());
+        }
+        for name in self.world_tile_names(world) {
+            let shadowed = self.overridden.get_mut(&name);
+            if let Some(shadowed) = shadowed {
+                shadowed.retain(|tile| tile.directory != world);
+            }
+            if self.tiles.get(&name).is_some_and(|tile| tile.directory == world) {
+                self.tiles.remove(&name);
+                // Promote the next tile in line
+                if let Some(shadowed) = self.overridden.get_mut(&name).filter(|shadowed| !shadowed.is_empty()) {
+                    let promoted = shadowed.remove(0);
+                    self.tiles.insert(name.clone(), promoted);
+                }
+            }
+            if self.overridden.get(&name).is_some_and(Vec::is_empty) {
+                self.overridden.remove(&name);
+            }
+        }
+    }
+
+    /// Moves everything from a database that only has a single world loaded into this one,
+    /// replacing anything this one had from that world.
+    ///
+    /// Vanilla tiles replace tiles of the same name, like they do when loading vanilla assets.
+    /// Custom tiles respect world precedence.
+    ///
+    /// Returns the names of the tiles that were moved.
+    pub(crate) fn absorb_world(&mut self, scratch: Database, world: &str) -> Vec<String> {
+        self.remove_world(world);
+        self.worlds.extend(scratch.worlds);
+        self.sprite_index.extend(scratch.sprite_index);
+        #[cfg(feature = "rendering")]
+        for palette in scratch.palettes.into_values().flatten() {
+            self.insert_palette(palette);
+        }
+        let names = scratch.tiles.keys().cloned().collect();
+        // Vanilla goes through precedence too, so reloading it doesn't clobber custom overrides
+        for (name, tile) in scratch.tiles {
+            self.insert_custom_tile(name, tile);
+        }
+        names
+    }
+
     /// Loads assets from a game directory.
-    /// 
+    ///
+    /// See [`Database::load_vanilla_from`] for details.
+    ///
     /// # Errors
     /// Bails if the path can't be read, or there's an issue parsing.
     pub fn load_vanilla(&mut self, path: impl AsRef<Path>) -> Result<(), LoadError> {
-        self.load_vanilla_values(path.as_ref().join("Data/values.lua"))?;
-        self.load_vanilla_objlist(path.as_ref().join("Data/Editor/editor_objectlist.lua"))
+        self.load_vanilla_from(&DirectorySource::new(path.as_ref()))
+    }
+
+    /// Loads assets from an asset source laid out like the game directory.
+    ///
+    /// If the `rendering` feature is enabled, the game's palettes are loaded from `Data/Palettes`.
+    /// 
+    /// # Errors
+    /// Bails if the source can't be read, or there's an issue parsing.
+    pub fn load_vanilla_from(&mut self, source: &dyn AssetSource) -> Result<(), LoadError> {
+        self.load_vanilla_values(source, Path::new("Data/values.lua"))?;
+        self.load_vanilla_objlist(source, Path::new("Data/Editor/editor_objectlist.lua"))?;
+        self.scan_sprites(source, &Database::sprite_directory("vanilla"), "vanilla")?;
+        #[cfg(feature = "rendering")]
+        self.load_palettes(source, Path::new("Data/Palettes"), "vanilla")?;
+        self.rebuild_aliases();
+        Ok(())
     }
 
     /// Parse a 2-element numeric tuple from Lua.
@@ -125,13 +409,9 @@ impl Database {
     }
 
     /// Loads assets from `values.lua`.
-    fn load_vanilla_values(&mut self, path: impl AsRef<Path>) -> Result<(), LoadError> {
+    fn load_vanilla_values(&mut self, source: &dyn AssetSource, path: &Path) -> Result<(), LoadError> {
         // Read the file
-        let path = path.as_ref();
-        let mut file_buf = String::new();
-        let mut f = fs::File::open(path)?;
-        f.read_to_string(&mut file_buf)?;
-        drop(f); // Close immediately
+        let file_buf = source.read_to_string(path)?;
 
         // Find the start and end of the tiles list
         let start = file_buf.find("tileslist =\n{\n\t")
@@ -174,11 +454,20 @@ impl Database {
             .ok_or(LoadError::InvalidLua("no name"))?
         ).trim_matches('"').to_string();        // Parse color
         // Parse color
-        let (color_x, color_y) = props.get("colour_active")
-            .or(props.get("colour"))
+        // Text has an active color for when it's in a rule, and uses its regular color otherwise
+        let (color_x, color_y) = props.get("colour")
             .and_then(Database::parse_lua_vec2)
             .ok_or(LoadError::InvalidLua("no color"))?;
-        let color = Color::Paletted {x: color_x, y: color_y};
+        let base_color = Color::Paletted {x: color_x, y: color_y};
+        let active_color = props.get("colour_active")
+            .map(Database::parse_lua_vec2)
+            .map(|opt| opt.ok_or(LoadError::InvalidLua("invalid active color")))
+            .transpose()?
+            .map(|(x, y)| Color::Paletted {x, y});
+        let (color, inactive_color) = match active_color {
+            Some(active_color) if active_color != base_color => (active_color, Some(base_color)),
+            _ => (base_color, None)
+        };
         // Parse tiling
         let tiling_num = props.get("tiling")
             .copied()
@@ -221,6 +510,7 @@ impl Database {
         // Construct it (finally)
         Ok((name, TileData {
             color,
+            inactive_color,
             sprite,
             directory: "vanilla".to_string(),
             tiling,
@@ -229,18 +519,19 @@ impl Database {
             grid_index,
             object_id: object_id.map(String::from),
             layer,
-            tags
+            tags,
+            aliases: BTreeSet::new(),
+            renamed_from: BTreeSet::new(),
+            sprite_size: None,
+            frame_count: None,
+            wobble_count: None
         }))
     }
 
     /// Loads assets from `editor_objlist.lua`.
-    fn load_vanilla_objlist(&mut self, path: impl AsRef<Path>) -> Result<(), LoadError> {
+    fn load_vanilla_objlist(&mut self, source: &dyn AssetSource, path: &Path) -> Result<(), LoadError> {
         // Read the file
-        let path = path.as_ref();
-        let mut file_buf = String::new();
-        let mut f = fs::File::open(path)?;
-        f.read_to_string(&mut file_buf)?;
-        drop(f); // Close immediately
+        let file_buf = source.read_to_string(path)?;
 
         // Find the start and end of the tiles list
         // Offset the start by the match string's length
@@ -275,6 +566,7 @@ impl Database {
             let entry = self.tiles.entry(name).or_default();
             *entry = TileData {
                 color: data.color,
+                inactive_color: data.inactive_color,
                 sprite: data.sprite,
                 directory: data.directory,
                 tiling: data.tiling,
@@ -284,8 +576,44 @@ impl Database {
                 object_id: data.object_id.or(entry.object_id.clone()),
                 layer: data.layer.or(entry.layer),
                 tags: data.tags.union(&entry.tags).cloned().collect(),
+                aliases: data.aliases.union(&entry.aliases).cloned().collect(),
+                renamed_from: data.renamed_from.union(&entry.renamed_from).cloned().collect(),
+                sprite_size: data.sprite_size.or(entry.sprite_size),
+                frame_count: data.frame_count.or(entry.frame_count),
+                wobble_count: data.wobble_count.or(entry.wobble_count)
             };
         }
         Ok(())
     }
 }
+
+/// Counts how many animation frames a sprite has in each direction, if its tiling has any.
+fn frame_count(files: &SpriteFiles, tiling: Tiling) -> Option<u8> {
+    let frames = files.keys().map(|(frame, _)| *frame).collect::<BTreeSet<_>>();
+    let count = match tiling {
+        Tiling::Animated => frames.len(),
+        Tiling::AnimDir => frames.range(0 .. 8).count(),
+        // Frame 7 is the sleeping sprite for facing up
+        Tiling::Character => frames.range(0 .. 7).count(),
+        _ => return None
+    };
+    u8::try_from(count).ok().filter(|count| *count > 0)
+}
+
+/// Splits a sprite's file name into its sprite name, frame, and wobble.
+fn parse_sprite_name(file_name: &str) -> Option<(&str, u8, u8)> {
+    let (rest, wobble) = file_name.strip_suffix(".png")?.rsplit_once('_')?;
+    let (sprite, frame) = rest.rsplit_once('_')?;
+    Some((sprite, frame.parse().ok()?, wobble.parse().ok()?))
+}
+
+/// Reads the width and height out of a PNG file's header.
+fn png_size(data: &[u8]) -> Option<(u32, u32)> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if !data.starts_with(SIGNATURE) || data.get(12 .. 16)? != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(data.get(16 .. 20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(data.get(20 .. 24)?.try_into().ok()?);
+    Some((width, height))
+}
@@ -0,0 +1,113 @@
+//! Handles comparing the contents of two databases.
+
+use std::collections::{BTreeMap, BTreeSet};
+use crate::database::Database;
+
+use super::structures::{Color, TileData, Tiling};
+
+/// The differences between the tiles of two databases.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DatabaseDiff {
+    /// Tiles that only exist in the newer database.
+    pub added: BTreeMap<String, TileData>,
+    /// Tiles that only exist in the older database.
+    pub removed: BTreeMap<String, TileData>,
+    /// Tiles that exist in both databases, but differ, with every field that changed.
+    pub changed: BTreeMap<String, Vec<TileChange>>
+}
+
+impl DatabaseDiff {
+    /// Returns whether the databases have the same tiles.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A single field of a tile that changed, with its old and new values.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+pub enum TileChange {
+    Color(Color, Color),
+    InactiveColor(Option<Color>, Option<Color>),
+    Sprite(String, String),
+    Directory(String, String),
+    Tiling(Tiling, Tiling),
+    Author(String, String),
+    TileIndex(Option<(u8, u8)>, Option<(u8, u8)>),
+    GridIndex(Option<(u8, u8)>, Option<(u8, u8)>),
+    ObjectId(Option<String>, Option<String>),
+    Layer(Option<u8>, Option<u8>),
+    Tags(BTreeSet<String>, BTreeSet<String>),
+    Aliases(BTreeSet<String>, BTreeSet<String>),
+    RenamedFrom(BTreeSet<String>, BTreeSet<String>),
+    SpriteSize(Option<(u32, u32)>, Option<(u32, u32)>),
+    FrameCount(Option<u8>, Option<u8>),
+    WobbleCount(Option<u8>, Option<u8>)
+}
+
+/// Helper macro for comparing the fields of two tiles
+macro_rules! compare_fields {
+    ($changes: ident, $old: ident, $new: ident; $($field: ident => $variant: ident),+) => {
+        $(
+            if $old.$field != $new.$field {
+                $changes.push(TileChange::$variant($old.$field.clone(), $new.$field.clone()));
+            }
+        )+
+    };
+}
+
+impl TileData {
+    /// Lists every field that differs between this tile and a newer version of it.
+    #[must_use]
+    pub fn diff(&self, new: &TileData) -> Vec<TileChange> {
+        let mut changes = Vec::new();
+        compare_fields! {
+            changes, self, new;
+            color => Color,
+            inactive_color => InactiveColor,
+            sprite => Sprite,
+            directory => Directory,
+            tiling => Tiling,
+            author => Author,
+            tile_index => TileIndex,
+            grid_index => GridIndex,
+            object_id => ObjectId,
+            layer => Layer,
+            tags => Tags,
+            aliases => Aliases,
+            renamed_from => RenamedFrom,
+            sprite_size => SpriteSize,
+            frame_count => FrameCount,
+            wobble_count => WobbleCount
+        }
+        changes
+    }
+}
+
+impl Database {
+    /// Compares the tiles in this database against a newer database.
+    ///
+    /// Only the tiles that take precedence are compared, so overridden tiles are ignored.
+    #[must_use]
+    pub fn diff(&self, new: &Database) -> DatabaseDiff {
+        let mut diff = DatabaseDiff::default();
+        for (name, old_tile) in &self.tiles {
+            match new.tiles.get(name) {
+                None => { diff.removed.insert(name.clone(), old_tile.clone()); },
+                Some(new_tile) => {
+                    let changes = old_tile.diff(new_tile);
+                    if !changes.is_empty() {
+                        diff.changed.insert(name.clone(), changes);
+                    }
+                }
+            }
+        }
+        diff.added.extend(
+            new.tiles.iter()
+                .filter(|(name, _)| !self.tiles.contains_key(*name))
+                .map(|(name, tile)| (name.clone(), tile.clone()))
+        );
+        diff
+    }
+}
@@ -0,0 +1,106 @@
+#![cfg(feature = "export")]
+//! Handles exporting the database into formats that other tools can read.
+
+use std::io::Write;
+use displaydoc::Display;
+use itertools::Itertools;
+use serde::Serialize;
+use thiserror::Error;
+use crate::database::Database;
+
+use super::structures::{Color, TileData};
+
+#[derive(Debug, Display, Error)]
+/// Error when exporting the database
+pub enum ExportError {
+    #[displaydoc("JSON error: {0}")]
+    /// Error when writing JSON
+    JsonError(#[from] serde_json::Error),
+    #[displaydoc("CSV error: {0}")]
+    /// Error when writing CSV
+    CsvError(#[from] csv::Error)
+}
+
+/// A single row of a CSV export.
+#[derive(Serialize)]
+struct CsvRow<'db> {
+    name: &'db str,
+    world: &'db str,
+    sprite: &'db str,
+    color: String,
+    inactive_color: Option<String>,
+    tiling: i8,
+    author: &'db str,
+    tile_index: Option<String>,
+    grid_index: Option<String>,
+    object_id: Option<&'db str>,
+    layer: Option<u8>,
+    tags: String,
+    aliases: String,
+    renamed_from: String,
+    sprite_size: Option<String>,
+    frame_count: Option<u8>,
+    wobble_count: Option<u8>
+}
+
+impl<'db> CsvRow<'db> {
+    fn new(name: &'db str, tile: &'db TileData) -> Self {
+        let pair = |(x, y): (u8, u8)| format!("{x},{y}");
+        let size = |(width, height): (u32, u32)| format!("{width},{height}");
+        // Use the same format that colors are parsed from
+        let color = |color| match color {
+            Color::Paletted { x, y } => pair((x, y)),
+            rgb @ Color::RGB { .. } => rgb.to_string()
+        };
+        Self {
+            name,
+            world: &tile.directory,
+            sprite: &tile.sprite,
+            color: color(tile.color),
+            inactive_color: tile.inactive_color.map(color),
+            tiling: tile.tiling as i8,
+            author: &tile.author,
+            tile_index: tile.tile_index.map(pair),
+            grid_index: tile.grid_index.map(pair),
+            object_id: tile.object_id.as_deref(),
+            layer: tile.layer,
+            tags: tile.tags.iter().join(","),
+            aliases: tile.aliases.iter().join(","),
+            renamed_from: tile.renamed_from.iter().join(","),
+            sprite_size: tile.sprite_size.map(size),
+            frame_count: tile.frame_count,
+            wobble_count: tile.wobble_count
+        }
+    }
+}
+
+impl Database {
+    /// Writes the entire database as JSON.
+    ///
+    /// Paletted colors are written as `[x, y]`, and RGB colors as `[r, g, b]`.
+    /// Palettes aren't included.
+    ///
+    /// # Errors
+    /// Bails if the JSON fails to write.
+    pub fn write_json(&self, writer: impl Write) -> Result<(), ExportError> {
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Writes every tile in the database as CSV, with one row per tile, sorted by name.
+    ///
+    /// Overridden tiles aren't included.
+    /// Colors and indices are written in the same format that [`Color`] is parsed from,
+    /// and lists are comma-separated.
+    ///
+    /// # Errors
+    /// Bails if the CSV fails to write.
+    pub fn write_csv(&self, writer: impl Write) -> Result<(), ExportError> {
+        let mut writer = csv::Writer::from_writer(writer);
+        for (name, tile) in self.tiles.iter().sorted_unstable_by_key(|(name, _)| *name) {
+            writer.serialize(CsvRow::new(name, tile))?;
+        }
+        writer.flush().map_err(csv::Error::from)?;
+        Ok(())
+    }
+}
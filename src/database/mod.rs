@@ -1,14 +1,33 @@
 //! Handles everything relating to data not included in the bot.
 
-use std::collections::HashMap;
-use crate::database::structures::TileData;
+use std::{cmp::Reverse, collections::{BTreeMap, HashMap}, path::{Path, PathBuf}};
+use crate::database::structures::{TileAlias, TileData, World};
+#[cfg(feature = "rendering")]
+use crate::database::structures::Palette;
 
 #[cfg(feature = "serde")]
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 
 
 pub mod structures;
+pub mod source;
 mod assets;
+mod diff;
+mod export;
+mod shared;
+mod watcher;
+
+pub use diff::{DatabaseDiff, TileChange};
+pub use shared::SharedDatabase;
+#[cfg(feature = "assets")]
+pub use assets::LoadError;
+#[cfg(feature = "export")]
+pub use export::ExportError;
+#[cfg(feature = "assets")]
+pub use watcher::{AssetWatcher, ChangeSet};
+
+/// The files of a single sprite, by the `(frame, wobble)` pair that each one is for.
+pub type SpriteFiles = BTreeMap<(u8, u8), PathBuf>;
 
 /// Chilly's internal database.
 ///
@@ -18,7 +37,58 @@ mod assets;
 #[derive(Debug, PartialEq, Eq, Default)]
 pub struct Database {
     /// A mapping of tile names to their data.
-    pub tiles: HashMap<String, TileData>
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_sorted"))]
+    pub tiles: HashMap<String, TileData>,
+    /// Tiles that were shadowed by a tile of the same name from a world with higher precedence,
+    /// ordered from highest to lowest precedence.
+    ///
+    /// These can still be looked up by world through [`Database::get_tile`].
+    #[cfg_attr(feature = "serde", serde(default, serialize_with = "serialize_sorted"))]
+    pub overridden: HashMap<String, Vec<TileData>>,
+    /// The order of precedence for custom worlds, from highest to lowest.
+    ///
+    /// When two worlds define the same tile, the one with higher precedence is kept.
+    /// Worlds that aren't listed here come after the listed ones,
+    /// in order of their [`World::priority`], then alphabetically.
+    /// Vanilla tiles always have the lowest precedence.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub world_precedence: Vec<String>,
+    /// A mapping of alternate tile names to the tiles that they refer to.
+    ///
+    /// This is derived from [`TileData::aliases`] and [`TileData::renamed_from`],
+    /// and is kept up to date when loading assets. See [`Database::rebuild_aliases`].
+    #[cfg_attr(feature = "serde", serde(default, serialize_with = "serialize_sorted"))]
+    pub aliases: HashMap<String, TileAlias>,
+    /// A mapping of world names to their metadata.
+    ///
+    /// Worlds without a `world.toml` are given the default metadata.
+    #[cfg_attr(feature = "serde", serde(default, serialize_with = "serialize_sorted"))]
+    pub worlds: HashMap<String, World>,
+    /// A mapping of world names to the sprites in that world,
+    /// with the path of the file for each `(frame, wobble)` pair that the sprite has.
+    ///
+    /// This is built while loading assets, so the renderer knows which sprite files exist
+    /// without having to look for them.
+    /// The paths are relative to the asset source the world was loaded from,
+    /// so this isn't serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub sprite_index: HashMap<String, BTreeMap<String, SpriteFiles>>,
+    /// A mapping of palette names to the palettes with that name,
+    /// ordered from highest to lowest world precedence.
+    #[cfg(feature = "rendering")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub palettes: HashMap<String, Vec<Palette>>
+}
+
+/// A tile that was defined by more than one world.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TileConflict {
+    /// The name of the tile.
+    pub name: String,
+    /// The world whose definition was kept.
+    pub world: String,
+    /// The worlds whose definitions were overridden, from highest to lowest precedence.
+    pub overridden: Vec<String>
 }
 
 impl Database {
@@ -27,4 +97,148 @@ impl Database {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Looks up a tile, optionally from a specific world.
+    ///
+    /// If a world is given, this will also find tiles that were overridden
+    /// by another world's tile of the same name.
+    #[must_use]
+    pub fn get_tile(&self, world: Option<&str>, name: &str) -> Option<&TileData> {
+        let Some(world) = world else {
+            return self.tiles.get(name);
+        };
+        self.tiles.get(name)
+            .filter(|tile| tile.directory == world)
+            .or_else(|| self.overridden.get(name)?.iter().find(|tile| tile.directory == world))
+    }
+
+    /// Looks up what tile an alternate name refers to.
+    #[must_use]
+    pub fn get_alias(&self, name: &str) -> Option<&TileAlias> {
+        self.aliases.get(name)
+    }
+
+    /// Rebuilds [`Database::aliases`] from the aliases of every tile in the database.
+    ///
+    /// Aliases never shadow an actual tile name.
+    /// If two tiles claim the same alias, the one from the world with higher precedence gets it.
+    pub fn rebuild_aliases(&mut self) {
+        let mut tiles = self.tiles.iter().collect::<Vec<_>>();
+        tiles.sort_unstable_by(|(a_name, a), (b_name, b)|
+            precedence_key(&self.world_precedence, &self.worlds, &a.directory)
+                .cmp(&precedence_key(&self.world_precedence, &self.worlds, &b.directory))
+                .then_with(|| a_name.cmp(b_name))
+        );
+        let mut aliases = HashMap::new();
+        for (name, tile) in tiles {
+            let names = tile.aliases.iter().map(|alias| (alias, false))
+                .chain(tile.renamed_from.iter().map(|alias| (alias, true)));
+            for (alias, deprecated) in names {
+                if self.tiles.contains_key(alias) {
+                    continue;
+                }
+                aliases.entry(alias.clone()).or_insert_with(|| TileAlias { name: name.clone(), deprecated });
+            }
+        }
+        self.aliases = aliases;
+    }
+
+    /// Looks up the files that a sprite from a world has, by `(frame, wobble)` pair.
+    ///
+    /// Returns [`None`] if the sprite isn't in the sprite index.
+    #[must_use]
+    pub fn sprite_files(&self, world: &str, sprite: &str) -> Option<&SpriteFiles> {
+        self.sprite_index.get(world)?.get(sprite)
+    }
+
+    /// Gets the directory that a world's sprite files are in,
+    /// relative to the asset source the world is loaded from.
+    #[must_use]
+    pub fn sprite_directory(world: &str) -> PathBuf {
+        if world == "vanilla" {
+            PathBuf::from("Data/Sprites")
+        } else {
+            Path::new(world).join("sprites")
+        }
+    }
+
+    /// Looks up a world's metadata.
+    #[must_use]
+    pub fn get_world(&self, name: &str) -> Option<&World> {
+        self.worlds.get(name)
+    }
+
+    /// Lists every world in the database, from highest to lowest precedence.
+    #[must_use]
+    pub fn list_worlds(&self) -> Vec<&World> {
+        let mut worlds = self.worlds.values().collect::<Vec<_>>();
+        worlds.sort_unstable_by(|a, b|
+            precedence_key(&self.world_precedence, &self.worlds, &a.name)
+                .cmp(&precedence_key(&self.world_precedence, &self.worlds, &b.name))
+        );
+        worlds
+    }
+
+    /// Looks up a palette, optionally from a specific world.
+    ///
+    /// If no world is given, the palette from the world with the highest precedence is used.
+    #[cfg(feature = "rendering")]
+    #[must_use]
+    pub fn get_palette(&self, world: Option<&str>, name: &str) -> Option<&Palette> {
+        let mut palettes = self.palettes.get(name)?.iter();
+        match world {
+            Some(world) => palettes.find(|palette| palette.world == world),
+            None => palettes.next()
+        }
+    }
+
+    /// Lists every palette in the database, sorted by name, then by world precedence.
+    #[cfg(feature = "rendering")]
+    #[must_use]
+    pub fn list_palettes(&self) -> Vec<&Palette> {
+        let mut names = self.palettes.keys().collect::<Vec<_>>();
+        names.sort_unstable();
+        names.into_iter()
+            .flat_map(|name| &self.palettes[name])
+            .collect()
+    }
+
+    /// Lists every tile conflict currently in the database, sorted by tile name.
+    #[must_use]
+    pub fn conflicts(&self) -> Vec<TileConflict> {
+        let mut conflicts = self.overridden.iter()
+            .filter(|(_, shadowed)| !shadowed.is_empty())
+            .filter_map(|(name, shadowed)| Some(TileConflict {
+                name: name.clone(),
+                world: self.tiles.get(name)?.directory.clone(),
+                overridden: shadowed.iter().map(|tile| tile.directory.clone()).collect()
+            }))
+            .collect::<Vec<_>>();
+        conflicts.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        conflicts
+    }
+}
+
+/// Gets a sorting key for a world, where lower keys have higher precedence.
+///
+/// Vanilla always comes last, so that any custom world can override it.
+fn precedence_key<'w>(
+    precedence: &[String],
+    worlds: &HashMap<String, World>,
+    world: &'w str
+) -> (bool, usize, Reverse<i32>, &'w str) {
+    let index = precedence.iter()
+        .position(|listed| listed == world)
+        .unwrap_or(precedence.len());
+    let priority = worlds.get(world).map_or(0, |world| world.priority);
+    (world == "vanilla", index, Reverse(priority), world)
+}
+
+/// Serializes a map in sorted order, so that serializing a database is deterministic.
+#[cfg(feature = "serde")]
+fn serialize_sorted<K: Ord + Serialize, V: Serialize, S: Serializer>(
+    map: &HashMap<K, V>,
+    serializer: S
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
 }
@@ -0,0 +1,50 @@
+//! Handles sharing a database between threads while it's being replaced.
+
+use std::sync::{Arc, PoisonError, RwLock};
+use crate::database::Database;
+
+/// A handle to a database that can be read from many threads at once,
+/// and replaced without disturbing anything still reading from it.
+///
+/// Readers take a [`snapshot`](SharedDatabase::snapshot) of the current database,
+/// which stays valid for as long as they hold onto it,
+/// even if a new database is [`swap`](SharedDatabase::swap)ped in in the meantime.
+///
+/// # Notes
+/// The database is never cloned - a new one should be loaded from scratch and swapped in.
+#[derive(Debug, Default)]
+pub struct SharedDatabase {
+    current: RwLock<Arc<Database>>
+}
+
+impl SharedDatabase {
+    /// Creates a handle to a database.
+    #[must_use]
+    pub fn new(database: Database) -> Self {
+        Self { current: RwLock::new(Arc::new(database)) }
+    }
+
+    /// Gets the current database.
+    ///
+    /// The returned database won't change, even if a new one is swapped in.
+    #[must_use]
+    pub fn snapshot(&self) -> Arc<Database> {
+        // Swapping can't leave the lock in a bad state, so poisoning is ignored
+        Arc::clone(&self.current.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Replaces the current database, returning the old one.
+    ///
+    /// Any snapshots of the old database stay valid until they're dropped.
+    pub fn swap(&self, database: Database) -> Arc<Database> {
+        let database = Arc::new(database);
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        std::mem::replace(&mut *current, database)
+    }
+}
+
+impl From<Database> for SharedDatabase {
+    fn from(database: Database) -> Self {
+        Self::new(database)
+    }
+}
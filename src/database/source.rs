@@ -0,0 +1,258 @@
+//! Abstracts over where assets are loaded from.
+//!
+//! Every path given to an [`AssetSource`] is relative to the root of the source,
+//! so the same assets can be loaded from a directory, an archive, or memory.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+    fs,
+    io,
+    path::{Component, Path, PathBuf},
+    time::SystemTime
+};
+#[cfg(feature = "archives")]
+use std::{
+    io::{Read, Seek},
+    sync::Mutex
+};
+
+/// Somewhere that assets can be read from.
+pub trait AssetSource: Debug + Send + Sync {
+    /// Reads the entire contents of a file.
+    ///
+    /// # Errors
+    /// Errors if the file doesn't exist or can't be read.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Lists the paths of everything directly inside a directory, in sorted order.
+    ///
+    /// # Errors
+    /// Errors if the directory doesn't exist or can't be read.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Checks whether a path is a directory.
+    fn is_dir(&self, path: &Path) -> bool;
+
+    /// Gets when a file was last modified, if the source keeps track of that.
+    ///
+    /// # Errors
+    /// Errors if the file doesn't exist or its metadata can't be read.
+    fn modified(&self, path: &Path) -> io::Result<Option<SystemTime>>;
+
+    /// Reads the entire contents of a file as UTF-8.
+    ///
+    /// # Errors
+    /// Errors if the file can't be read, or isn't valid UTF-8.
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// Assets from a directory on disk.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DirectorySource {
+    /// The directory that paths are relative to.
+    pub root: PathBuf
+}
+
+impl DirectorySource {
+    /// Creates a source for a directory.
+    #[must_use]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl AssetSource for DirectorySource {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(self.root.join(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut entries = fs::read_dir(self.root.join(path))?
+            .map(|entry| Ok(path.join(entry?.file_name())))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort_unstable();
+        Ok(entries)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.root.join(path).is_dir()
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<Option<SystemTime>> {
+        fs::metadata(self.root.join(path))?.modified().map(Some)
+    }
+}
+
+/// Normalizes a path so that equivalent paths compare equal.
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| !matches!(component, Component::CurDir | Component::RootDir))
+        .collect()
+}
+
+/// Lists the children of a directory, given every file path in a source.
+fn list_children<'p>(files: impl Iterator<Item = &'p Path>, path: &Path) -> io::Result<Vec<PathBuf>> {
+    let path = normalize(path);
+    let children = files
+        .filter_map(|file| file.strip_prefix(&path).ok())
+        .filter_map(|rest| rest.components().next())
+        .map(|child| path.join(child))
+        .collect::<BTreeSet<_>>();
+    if children.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("no directory at {}", path.display())));
+    }
+    Ok(children.into_iter().collect())
+}
+
+/// Checks whether a path is a directory, given every file path in a source.
+fn has_children<'p>(mut files: impl Iterator<Item = &'p Path>, path: &Path) -> bool {
+    let path = normalize(path);
+    files.any(|file| file != path && file.starts_with(&path))
+}
+
+/// Assets held in memory.
+///
+/// Directories are implied by the paths of the files inside of them.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MemorySource {
+    /// A mapping of file paths to their contents and when they were last modified.
+    pub files: BTreeMap<PathBuf, (Vec<u8>, SystemTime)>
+}
+
+impl MemorySource {
+    /// Creates an empty source.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file to the source, replacing it if it already exists.
+    pub fn insert(&mut self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) {
+        self.files.insert(normalize(path.as_ref()), (contents.into(), SystemTime::now()));
+    }
+
+    /// Removes a file from the source, returning its contents if it existed.
+    pub fn remove(&mut self, path: impl AsRef<Path>) -> Option<Vec<u8>> {
+        self.files.remove(&normalize(path.as_ref())).map(|(contents, _)| contents)
+    }
+
+    /// Reads every file in a tar archive into memory.
+    ///
+    /// Tar archives can't be read from out of order, so this is used instead of a dedicated source.
+    ///
+    /// # Errors
+    /// Errors if the archive can't be read.
+    #[cfg(feature = "archives")]
+    pub fn from_tar(reader: impl Read) -> io::Result<Self> {
+        let mut source = Self::new();
+        for entry in tar::Archive::new(reader).entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let path = normalize(&entry.path()?);
+            let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(entry.header().mtime()?);
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents)?;
+            source.files.insert(path, (contents, modified));
+        }
+        Ok(source)
+    }
+}
+
+impl AssetSource for MemorySource {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files.get(&normalize(path))
+            .map(|(contents, _)| contents.clone())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no file at {}", path.display())))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        list_children(self.files.keys().map(PathBuf::as_path), path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        has_children(self.files.keys().map(PathBuf::as_path), path)
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<Option<SystemTime>> {
+        self.files.get(&normalize(path))
+            .map(|(_, modified)| Some(*modified))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no file at {}", path.display())))
+    }
+}
+
+/// Assets from a zip archive, read from as they're needed.
+#[cfg(feature = "archives")]
+#[derive(Debug)]
+pub struct ZipSource<R: Read + Seek> {
+    archive: Mutex<zip::ZipArchive<R>>,
+    /// The paths of every file in the archive.
+    files: BTreeSet<PathBuf>
+}
+
+#[cfg(feature = "archives")]
+impl<R: Read + Seek> ZipSource<R> {
+    /// Opens a zip archive.
+    ///
+    /// # Errors
+    /// Errors if the archive can't be read.
+    pub fn new(reader: R) -> io::Result<Self> {
+        let archive = zip::ZipArchive::new(reader)?;
+        let files = archive.file_names()
+            .filter(|name| !name.ends_with('/'))
+            .map(|name| normalize(Path::new(name)))
+            .collect();
+        Ok(Self { archive: Mutex::new(archive), files })
+    }
+
+    /// Gets the name of a file inside of the archive.
+    fn entry_name(path: &Path) -> String {
+        normalize(path).components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+#[cfg(feature = "archives")]
+impl ZipSource<fs::File> {
+    /// Opens a zip archive on disk.
+    ///
+    /// # Errors
+    /// Errors if the archive can't be opened or read.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(fs::File::open(path)?)
+    }
+}
+
+#[cfg(feature = "archives")]
+impl<R: Read + Seek + Send + Debug> AssetSource for ZipSource<R> {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let mut archive = self.archive.lock().map_err(|_| io::Error::other("zip archive lock was poisoned"))?;
+        let mut file = archive.by_name(&Self::entry_name(path))?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        list_children(self.files.iter().map(PathBuf::as_path), path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        has_children(self.files.iter().map(PathBuf::as_path), path)
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<Option<SystemTime>> {
+        if !self.files.contains(&normalize(path)) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no file at {}", path.display())));
+        }
+        // Zip timestamps have no time zone, so they can't be trusted
+        Ok(None)
+    }
+}
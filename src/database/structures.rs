@@ -6,6 +6,8 @@ use core::{
 };
 use std::collections::BTreeSet;
 use std::num::ParseIntError;
+#[cfg(feature = "rendering")]
+use std::path::PathBuf;
 use displaydoc::Display;
 
 #[cfg(feature = "serde")]
@@ -252,6 +254,11 @@ impl<'de> Deserialize<'de> for Color {
 pub struct TileData {
     /// The color of the tile
     pub color: Color,
+    /// The color of the tile when it's inactive, if it's different from its usual color
+    ///
+    /// This is used for text, which is dimmed when it's not part of a rule.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub inactive_color: Option<Color>,
     /// The sprite filename of the tile
     pub sprite: String,
     /// The directory that the tile resides in
@@ -278,13 +285,40 @@ pub struct TileData {
     /// The tags of this sprite
     #[cfg_attr(feature = "serde", serde(default))]
     // Use a BTreeSet to allow hashing
-    pub tags: BTreeSet<String>
+    pub tags: BTreeSet<String>,
+    /// Other names that this tile can be referred to by
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub aliases: BTreeSet<String>,
+    /// Names that this tile used to have, which still work but are deprecated
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub renamed_from: BTreeSet<String>,
+    /// The width and height of the tile's sprites, in pixels
+    // If these aren't specified, they're filled in from the sprite files when loading.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sprite_size: Option<(u32, u32)>,
+    /// How many animation frames the tile has in each direction
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub frame_count: Option<u8>,
+    /// How many wobble frames each of the tile's sprites has
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub wobble_count: Option<u8>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// Another name that a tile can be looked up by.
+pub struct TileAlias {
+    /// The name of the tile that this alias refers to
+    pub name: String,
+    /// Whether the alias is an old name of the tile, and shouldn't be used anymore
+    pub deprecated: bool
 }
 
 impl Default for TileData {
     fn default() -> Self {
         Self {
             color: Color::Paletted {x: 0, y: 3},
+            inactive_color: None,
             sprite: "error".to_string(),
             directory: "vanilla".to_string(),
             tiling: Tiling::None,
@@ -293,7 +327,52 @@ impl Default for TileData {
             grid_index: None,
             object_id: None,
             layer: None,
-            tags: BTreeSet::new()
+            tags: BTreeSet::new(),
+            aliases: BTreeSet::new(),
+            renamed_from: BTreeSet::new(),
+            sprite_size: None,
+            frame_count: None,
+            wobble_count: None
         }
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+/// Metadata about a custom world, read from its `world.toml`.
+pub struct World {
+    /// The name of the world's directory
+    // Like TileData::directory, this is set when loading.
+    pub name: String,
+    /// The name to show for the world, if it has one
+    pub display_name: Option<String>,
+    /// Who created the world
+    pub authors: Vec<String>,
+    /// A description of the world
+    pub description: Option<String>,
+    /// The license that the world's assets are under
+    pub license: Option<String>,
+    /// The palette that scenes using this world should default to
+    pub default_palette: Option<String>,
+    /// The world's load priority.
+    /// Worlds with a higher priority take precedence over ones with a lower priority.
+    pub priority: i32
+}
+
+/// The width and height that every palette must have.
+pub const PALETTE_SIZE: (u32, u32) = (7, 5);
+
+#[cfg(feature = "rendering")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A palette that tiles can take their colors from.
+pub struct Palette {
+    /// The name of the palette.
+    pub name: String,
+    /// The world that the palette is from.
+    pub world: String,
+    /// The path that the palette was loaded from, relative to its asset source.
+    pub source: PathBuf,
+    /// The palette's image.
+    pub image: RgbaImage
+}
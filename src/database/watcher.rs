@@ -0,0 +1,216 @@
+#![cfg(feature = "assets")]
+//! Handles reloading the database when asset files change.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::Path,
+    sync::Arc,
+    time::SystemTime
+};
+use crate::database::{source::{AssetSource, DirectorySource}, Database, LoadError};
+
+use super::structures::TileData;
+
+/// Keeps track of when asset files were last modified,
+/// so a [`Database`] can reload only what changed.
+///
+/// This doesn't watch the filesystem by itself -
+/// call [`Database::reload_changed`] on an interval,
+/// or whenever a filesystem notification comes in.
+#[derive(Debug, Clone, Default)]
+pub struct AssetWatcher {
+    /// The source of custom worlds being watched.
+    custom_source: Option<Arc<dyn AssetSource>>,
+    /// The source of vanilla assets being watched.
+    vanilla_source: Option<Arc<dyn AssetSource>>,
+    /// The last seen modification times of each world's `sprites.toml` or `world.toml`, by world name.
+    worlds: HashMap<String, SystemTime>,
+    /// The last seen modification times of the vanilla Lua files.
+    vanilla: Vec<Option<SystemTime>>
+}
+
+/// The tiles that changed after reloading the database.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ChangeSet {
+    /// Tiles that were added, with their new data.
+    pub added: BTreeMap<String, TileData>,
+    /// Tiles that were removed, with their old data.
+    pub removed: BTreeMap<String, TileData>,
+    /// Tiles that were modified, with their old and new data.
+    pub modified: BTreeMap<String, (TileData, TileData)>
+}
+
+impl ChangeSet {
+    /// Returns whether nothing changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// The vanilla files that get read by [`Database::load_vanilla`].
+const VANILLA_FILES: [&str; 2] = ["Data/values.lua", "Data/Editor/editor_objectlist.lua"];
+
+/// Gets the modification time of a file, if it exists and the source keeps track of it.
+fn modified_time(source: &dyn AssetSource, path: &Path) -> Option<SystemTime> {
+    source.modified(path).ok().flatten()
+}
+
+impl AssetWatcher {
+    /// Creates a watcher that doesn't watch anything.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts watching a directory of custom worlds.
+    ///
+    /// This should be the same path that was given to [`Database::load_custom`],
+    /// as the current state of the directory is assumed to already be loaded.
+    ///
+    /// # Errors
+    /// Bails if the directory can't be read.
+    pub fn watch_custom(&mut self, path: impl AsRef<Path>) -> Result<(), LoadError> {
+        self.watch_custom_from(Arc::new(DirectorySource::new(path.as_ref())))
+    }
+
+    /// Starts watching an asset source of custom worlds.
+    ///
+    /// This should be the same source that was given to [`Database::load_custom_from`].
+    /// Worlds in sources that don't keep track of modification times are never reloaded.
+    ///
+    /// # Errors
+    /// Bails if the source can't be read.
+    pub fn watch_custom_from(&mut self, source: Arc<dyn AssetSource>) -> Result<(), LoadError> {
+        self.worlds = Self::scan_worlds(source.as_ref())?;
+        self.custom_source = Some(source);
+        Ok(())
+    }
+
+    /// Starts watching a game directory.
+    ///
+    /// This should be the same path that was given to [`Database::load_vanilla`],
+    /// as the current state of the directory is assumed to already be loaded.
+    pub fn watch_vanilla(&mut self, path: impl AsRef<Path>) {
+        self.watch_vanilla_from(Arc::new(DirectorySource::new(path.as_ref())));
+    }
+
+    /// Starts watching an asset source laid out like the game directory.
+    ///
+    /// This should be the same source that was given to [`Database::load_vanilla_from`].
+    pub fn watch_vanilla_from(&mut self, source: Arc<dyn AssetSource>) {
+        self.vanilla = Self::scan_vanilla(source.as_ref());
+        self.vanilla_source = Some(source);
+    }
+
+    /// Gets the modification times of every world in an asset source.
+    fn scan_worlds(source: &dyn AssetSource) -> Result<HashMap<String, SystemTime>, LoadError> {
+        Ok(
+            Database::world_paths(source)?
+                .into_iter()
+                .filter_map(|world| {
+                    let sprites_time = modified_time(source, &world.join("sprites.toml"))?;
+                    let metadata_time = modified_time(source, &world.join("world.toml"));
+                    Some((
+                        world.file_name()?.to_string_lossy().to_string(),
+                        metadata_time.map_or(sprites_time, |time| time.max(sprites_time))
+                    ))
+                })
+                .collect()
+        )
+    }
+
+    /// Gets the modification times of the vanilla files in an asset source.
+    fn scan_vanilla(source: &dyn AssetSource) -> Vec<Option<SystemTime>> {
+        VANILLA_FILES.iter()
+            .map(|file| modified_time(source, Path::new(file)))
+            .collect()
+    }
+}
+
+impl Database {
+    /// Reloads any worlds or vanilla files that changed since the watcher last checked.
+    ///
+    /// Only the changed files are re-read, and the tiles that changed as a result are returned.
+    ///
+    /// # Errors
+    /// Bails if a changed file fails to load.
+    /// Neither the database nor the watcher are changed in this case, so the next call will try again.
+    pub fn reload_changed(&mut self, watcher: &mut AssetWatcher) -> Result<ChangeSet, LoadError> {
+        // Find what changed
+        let mut changed_worlds = BTreeSet::new();
+        let mut new_worlds = HashMap::new();
+        if let Some(custom_source) = &watcher.custom_source {
+            new_worlds = AssetWatcher::scan_worlds(custom_source.as_ref())?;
+            changed_worlds.extend(
+                new_worlds.iter()
+                    .filter(|(world, time)| watcher.worlds.get(*world) != Some(time))
+                    .map(|(world, _)| world.clone())
+            );
+            changed_worlds.extend(
+                watcher.worlds.keys()
+                    .filter(|world| !new_worlds.contains_key(*world))
+                    .cloned()
+            );
+        }
+        let mut new_vanilla = Vec::new();
+        let mut vanilla_changed = false;
+        if let Some(vanilla_source) = &watcher.vanilla_source {
+            new_vanilla = AssetWatcher::scan_vanilla(vanilla_source.as_ref());
+            vanilla_changed = new_vanilla != watcher.vanilla;
+        }
+
+        // Remember the old state of anything that could change
+        let mut affected = changed_worlds.iter()
+            .flat_map(|world| self.world_tile_names(world))
+            .collect::<BTreeSet<_>>();
+        if vanilla_changed {
+            affected.extend(self.world_tile_names("vanilla"));
+        }
+        let mut old_tiles = affected.iter()
+            .filter_map(|name| Some((name.clone(), self.tiles.get(name)?.clone())))
+            .collect::<HashMap<_, _>>();
+
+        // Reload into scratch databases first, so that a file failing to load leaves this one untouched
+        let mut reloaded = Vec::new();
+        if let Some(custom_source) = &watcher.custom_source {
+            for world in changed_worlds.iter().filter(|world| new_worlds.contains_key(*world)) {
+                let mut scratch = Database::new();
+                scratch.load_custom_path(custom_source.as_ref(), Path::new(world))?;
+                reloaded.push((world.as_str(), scratch));
+            }
+        }
+        if let (true, Some(vanilla_source)) = (vanilla_changed, &watcher.vanilla_source) {
+            let mut scratch = Database::new();
+            scratch.load_vanilla_from(vanilla_source.as_ref())?;
+            reloaded.push(("vanilla", scratch));
+        }
+
+        // Everything loaded, so swap it in
+        for world in &changed_worlds {
+            self.remove_world(world);
+        }
+        for (world, scratch) in reloaded {
+            affected.extend(self.absorb_world(scratch, world));
+        }
+        watcher.worlds = new_worlds;
+        watcher.vanilla = new_vanilla;
+        if !changed_worlds.is_empty() || vanilla_changed {
+            self.rebuild_aliases();
+        }
+
+        // Compare the old and new states
+        let mut changes = ChangeSet::default();
+        for name in affected {
+            match (old_tiles.remove(&name), self.tiles.get(&name)) {
+                (None, Some(new)) => { changes.added.insert(name, new.clone()); },
+                (Some(old), None) => { changes.removed.insert(name, old); },
+                (Some(old), Some(new)) if &old != new => {
+                    changes.modified.insert(name, (old, new.clone()));
+                },
+                _ => ()
+            }
+        }
+        Ok(changes)
+    }
+}
@@ -0,0 +1,182 @@
+//! Handles exporting scenes as levels.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    io::{self, Write},
+    path::Path
+};
+use flate2::{write::ZlibEncoder, Compression};
+use crate::{
+    arguments::{Flag, FlagName},
+    database::structures::{TileData, Tiling},
+    solidify::{SkeletalScene, TileSkeletonType},
+    structures::Position
+};
+use super::{tile_id, Direction, EMPTY_CELL};
+
+/// The most map layers that a level can have in the game.
+const MAX_MAP_LAYERS: usize = 3;
+
+/// A scene that was exported as a level.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ExportedLevel {
+    /// The contents of the level's `.l` file.
+    pub map: Vec<u8>,
+    /// The contents of the level's `.ld` file.
+    pub metadata: String,
+    /// Tiles in the scene that couldn't be put into the level.
+    pub skipped: Vec<SkippedTile>
+}
+
+/// A tile that was left out when exporting a scene as a level.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SkippedTile {
+    /// The position of the tile in the scene.
+    pub position: Position<usize>,
+    /// The name of the tile, as written in the scene.
+    pub name: String,
+    /// Why the tile was left out.
+    pub reason: SkipReason
+}
+
+/// The reason that a tile was left out of an exported level.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SkipReason {
+    /// The tile isn't in the database, and would've been generated.
+    Generated,
+    /// The tile doesn't exist in the game, like tiles from custom worlds.
+    NotInGame,
+    /// The tile's cell already has as many objects as a level can hold.
+    TooManyLayers
+}
+
+/// Compresses a block of data and writes it with its length.
+fn write_compressed(out: &mut Vec<u8>, data: &[u8]) -> io::Result<()> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    let compressed = encoder.finish()?;
+    out.extend(u32::try_from(compressed.len()).map_err(io::Error::other)?.to_le_bytes());
+    out.extend(compressed);
+    Ok(())
+}
+
+impl ExportedLevel {
+    /// Exports the first frame of a scene as a level.
+    ///
+    /// Objects in the same cell are put into separate map layers, in order of their Z position.
+    ///
+    /// # Errors
+    /// Errors if the level data fails to compress, or the scene is too large for a level.
+    pub fn from_scene(scene: &SkeletalScene, name: &str) -> io::Result<ExportedLevel> {
+        let mut exported = ExportedLevel::default();
+        let (width, height) = (scene.map.width, scene.map.height);
+
+        // Sort the objects so that stacked objects keep their order
+        let objects = scene.map.objects.iter()
+            .filter(|(pos, _)| pos.t == 0)
+            .collect::<BTreeMap<_, _>>();
+        let mut layers: Vec<(Vec<u16>, Vec<u8>)> = Vec::new();
+        let mut cell_heights = vec![0; width * height];
+        let mut used: BTreeMap<&str, &TileData> = BTreeMap::new();
+        for (pos, skel) in objects {
+            let mut skip = |reason| exported.skipped.push(SkippedTile {
+                position: *pos,
+                name: skel.span.as_str().to_string(),
+                reason
+            });
+            let TileSkeletonType::Existing(data) = skel.data else {
+                skip(SkipReason::Generated);
+                continue;
+            };
+            // Custom tiles can have a tile index, but it doesn't point into the game's tiles
+            let Some(tile_index) = data.tile_index.filter(|_| data.directory == "vanilla") else {
+                skip(SkipReason::NotInGame);
+                continue;
+            };
+            let cell = pos.y * width + pos.x;
+            let layer = cell_heights[cell];
+            if layer >= MAX_MAP_LAYERS {
+                skip(SkipReason::TooManyLayers);
+                continue;
+            }
+            cell_heights[cell] += 1;
+            if layers.len() <= layer {
+                layers.push((vec![EMPTY_CELL; width * height], vec![0; width * height]));
+            }
+            let direction = match data.tiling {
+                Tiling::Directional | Tiling::Character | Tiling::AnimDir =>
+                    Direction::try_from(skel.animation_frame.0 / 8).unwrap_or_default(),
+                _ => Direction::Right
+            };
+            layers[layer].0[cell] = tile_id(tile_index);
+            layers[layer].1[cell] = direction as u8;
+            if let Some(object_id) = &data.object_id {
+                used.insert(object_id, data);
+            }
+        }
+        if layers.is_empty() {
+            layers.push((vec![EMPTY_CELL; width * height], vec![0; width * height]));
+        }
+
+        // Write the map
+        let layer_width = u32::try_from(width).map_err(io::Error::other)?;
+        let layer_height = u32::try_from(height).map_err(io::Error::other)?;
+        let mut layer_data = Vec::new();
+        layer_data.extend(u16::try_from(layers.len()).map_err(io::Error::other)?.to_le_bytes());
+        for (tiles, directions) in &layers {
+            layer_data.extend(layer_width.to_le_bytes());
+            layer_data.extend(layer_height.to_le_bytes());
+            // Layer metadata that we don't use (position, scale, etc.)
+            layer_data.extend([0; 32]);
+            layer_data.push(2); // Number of data blocks
+            layer_data.extend(b"MAIN");
+            let tiles = tiles.iter().flat_map(|id| id.to_le_bytes()).collect::<Vec<_>>();
+            write_compressed(&mut layer_data, &tiles)?;
+            layer_data.extend(b"DATA");
+            layer_data.push(1);
+            layer_data.extend(u32::try_from(directions.len()).map_err(io::Error::other)?.to_le_bytes());
+            write_compressed(&mut layer_data, directions)?;
+        }
+        exported.map.extend(b"ACHTUNG!");
+        exported.map.extend(261u16.to_le_bytes()); // Format version
+        exported.map.extend(b"MAP ");
+        exported.map.extend(2u32.to_le_bytes());
+        exported.map.extend(0u16.to_le_bytes());
+        exported.map.extend(b"LAYR");
+        exported.map.extend(u32::try_from(layer_data.len()).map_err(io::Error::other)?.to_le_bytes());
+        exported.map.extend(layer_data);
+
+        // Write the metadata
+        let palette = match scene.flags.get(&FlagName::Palette) {
+            Some(Flag::Palette(palette)) => palette.name.as_str(),
+            _ => "default"
+        };
+        // Writing to a String can't fail
+        let metadata = &mut exported.metadata;
+        let _ = write!(metadata, "[general]\nname={name}\npalette={palette}.png\n");
+        // The editor's object palette
+        let _ = write!(metadata, "[currobjlist]\ntotal={}\n", used.len());
+        for (index, (object_id, data)) in used.into_iter().enumerate() {
+            let _ = writeln!(metadata, "{index}id={object_id}");
+            if let Some((x, y)) = data.tile_index {
+                let _ = writeln!(metadata, "{index}tile={x},{y}");
+            }
+            if let Some((x, y)) = data.grid_index {
+                let _ = writeln!(metadata, "{index}gridpos={x},{y}");
+            }
+        }
+        Ok(exported)
+    }
+
+    /// Writes the level's `.l` and `.ld` files next to each other.
+    ///
+    /// # Errors
+    /// Errors if either file fails to write.
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        fs::write(path.with_extension("l"), &self.map)?;
+        fs::write(path.with_extension("ld"), &self.metadata)
+    }
+}
@@ -0,0 +1,268 @@
+#![cfg(feature = "levels")]
+//! Handles converting between levels from Baba Is You and scenes.
+
+mod structures;
+mod export;
+pub use structures::{Direction, Level, LevelError, LevelObject, UnknownObject};
+pub use export::{ExportedLevel, SkippedTile, SkipReason};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{self, Read},
+    path::Path
+};
+use flate2::read::ZlibDecoder;
+use pest::Span;
+use crate::{
+    arguments::{Flag, FlagName, PaletteName, Variant},
+    database::{
+        structures::{TileData, Tiling},
+        Database
+    },
+    parser::{RawScene, RawTile},
+    structures::{ObjectMap, Position}
+};
+
+/// The tile index that marks an empty cell in a level's map.
+const EMPTY_CELL: u16 = 0xFFFF;
+
+/// Converts a tile's index into Baba Is You's tile grid into the index stored in level files.
+pub(crate) fn tile_id((x, y): (u8, u8)) -> u16 {
+    u16::from_le_bytes([x, y])
+}
+
+/// A single map layer of a level file.
+struct MapLayer {
+    width: usize,
+    height: usize,
+    tiles: Vec<u16>,
+    directions: Vec<u8>
+}
+
+/// A cursor over the bytes of a level file.
+struct ByteReader<'a> {
+    data: &'a [u8],
+    position: usize
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], LevelError> {
+        let bytes = self.data.get(self.position .. self.position + count)
+            .ok_or(LevelError::UnexpectedEof(self.position, count))?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, LevelError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, LevelError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, LevelError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Checks that the next bytes are a given header.
+    fn expect(&mut self, header: &'static str) -> Result<(), LevelError> {
+        let position = self.position;
+        if self.take(header.len())? != header.as_bytes() {
+            return Err(LevelError::MissingHeader(header, position));
+        }
+        Ok(())
+    }
+
+    /// Reads a length-prefixed block of zlib-compressed data.
+    fn compressed(&mut self) -> Result<Vec<u8>, LevelError> {
+        let len = self.u32()? as usize;
+        let mut decompressed = Vec::new();
+        ZlibDecoder::new(self.take(len)?).read_to_end(&mut decompressed)?;
+        Ok(decompressed)
+    }
+}
+
+/// Parses the map layers out of a `.l` file.
+fn parse_map(data: &[u8]) -> Result<Vec<MapLayer>, LevelError> {
+    let mut reader = ByteReader { data, position: 0 };
+    reader.expect("ACHTUNG!")?;
+    reader.u16()?; // Format version
+    reader.expect("MAP ")?;
+    let map_len = reader.u32()? as usize;
+    reader.take(map_len)?;
+    reader.expect("LAYR")?;
+    reader.u32()?; // Size of the layer chunk
+    let layer_count = reader.u16()?;
+    (0 .. usize::from(layer_count)).map(|index| {
+        let width = reader.u32()? as usize;
+        let height = reader.u32()? as usize;
+        // Layer metadata that we don't use (position, scale, etc.)
+        reader.take(32)?;
+        let blocks = reader.u8()?;
+        reader.expect("MAIN")?;
+        let tiles = reader.compressed()?
+            .chunks_exact(2)
+            .map(|id| u16::from_le_bytes([id[0], id[1]]))
+            .collect::<Vec<_>>();
+        if tiles.len() != width * height {
+            return Err(LevelError::WrongLayerSize(index, tiles.len(), width, height));
+        }
+        let directions = if blocks >= 2 {
+            reader.expect("DATA")?;
+            reader.take(5)?;
+            reader.compressed()?
+        } else {
+            Vec::new()
+        };
+        Ok(MapLayer { width, height, tiles, directions })
+    }).collect()
+}
+
+/// Parses a `.ld` file into its sections and their keys.
+fn parse_metadata(data: &str) -> HashMap<&str, HashMap<&str, &str>> {
+    let mut sections: HashMap<&str, HashMap<&str, &str>> = HashMap::new();
+    let mut current = "";
+    for line in data.lines().map(str::trim) {
+        if let Some(section) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            current = section;
+        } else if let Some((key, value)) = line.split_once('=') {
+            sections.entry(current).or_default().insert(key, value);
+        }
+    }
+    sections
+}
+
+impl Level {
+    /// Opens a level from its `.l` file, along with the `.ld` file next to it if there is one.
+    ///
+    /// # Errors
+    /// Errors if a file can't be read, or the level is malformed.
+    pub fn open(path: impl AsRef<Path>, db: &Database) -> Result<Level, LevelError> {
+        let path = path.as_ref();
+        let map = fs::read(path.with_extension("l"))?;
+        let metadata = match fs::read_to_string(path.with_extension("ld")) {
+            Ok(metadata) => Some(metadata),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into())
+        };
+        Level::from_bytes(&map, metadata.as_deref(), db)
+    }
+
+    /// Reads a level from the contents of its `.l` and `.ld` files.
+    ///
+    /// Objects are resolved to tiles by their index into the game's tile grid.
+    /// If the level renames an object, the renamed tile is used if it's in the database.
+    ///
+    /// # Errors
+    /// Errors if the level is malformed.
+    pub fn from_bytes(map: &[u8], metadata: Option<&str>, db: &Database) -> Result<Level, LevelError> {
+        let layers = parse_map(map)?;
+        let metadata = metadata.map(parse_metadata).unwrap_or_default();
+        let general = metadata.get("general");
+        let tile_section = metadata.get("tiles");
+
+        // Map each tile index to the tile it represents,
+        // preferring tiles with an object ID as those come from the game's tile list
+        let mut indexed = db.tiles.iter()
+            .filter_map(|(name, data)| Some((tile_id(data.tile_index?), name, data)))
+            .collect::<Vec<_>>();
+        indexed.sort_unstable_by_key(|(_, name, data)| (data.object_id.is_some(), *name));
+        let by_id: HashMap<u16, (&String, &TileData)> = indexed.into_iter()
+            .map(|(id, name, data)| (id, (name, data)))
+            .collect();
+
+        let mut level = Level {
+            name: general.and_then(|general| general.get("name")).copied().unwrap_or_default().to_string(),
+            palette: general.and_then(|general| general.get("palette"))
+                .map(|palette| palette.strip_suffix(".png").unwrap_or(palette).to_string()),
+            width: layers.first().map_or(0, |layer| layer.width),
+            height: layers.first().map_or(0, |layer| layer.height),
+            ..Default::default()
+        };
+        for (map_layer, layer) in layers.iter().enumerate() {
+            for (index, &id) in layer.tiles.iter().enumerate() {
+                if id == EMPTY_CELL {
+                    continue;
+                }
+                let (x, y) = (index % layer.width, index / layer.width);
+                let Some((name, data)) = by_id.get(&id) else {
+                    level.unknown.push(UnknownObject { x, y, id });
+                    continue;
+                };
+                // Check if the level renamed this object
+                let renamed = data.object_id.as_ref()
+                    .and_then(|object_id| tile_section?.get(format!("{object_id}_name").as_str()))
+                    .filter(|renamed| db.tiles.contains_key(**renamed));
+                let direction = layer.directions.get(index)
+                    .and_then(|&direction| Direction::try_from(direction).ok())
+                    .unwrap_or_default();
+                level.objects.push(LevelObject {
+                    x, y, map_layer,
+                    name: renamed.map_or_else(|| (*name).clone(), ToString::to_string),
+                    direction
+                });
+            }
+        }
+        Ok(level)
+    }
+
+    /// Turns this level into a scene, ready to be solidified with [`TileDefault::Tile`].
+    ///
+    /// Objects in the same cell are stacked by their tile's layer,
+    /// then by the map layer that they came from.
+    ///
+    /// # Panics
+    /// Shouldn't panic, but does have `expect` in the body.
+    ///
+    /// [`TileDefault::Tile`]: crate::solidify::TileDefault::Tile
+    #[must_use]
+    pub fn to_raw_scene(&self, db: &Database) -> RawScene<'_> {
+        let mut cells = BTreeMap::<_, Vec<_>>::new();
+        for object in &self.objects {
+            cells.entry((object.x, object.y)).or_default().push((object, db.tiles.get(&object.name)));
+        }
+        let mut objects = HashMap::new();
+        for ((x, y), mut cell) in cells {
+            cell.sort_by_key(|(object, data): &(&LevelObject, Option<&TileData>)|
+                (data.and_then(|data| data.layer), object.map_layer)
+            );
+            for (z, (object, data)) in cell.into_iter().enumerate() {
+                // Only give directions to tiles that have sprites for them
+                let directional = data.is_some_and(|data| matches!(
+                    data.tiling, Tiling::Directional | Tiling::Character | Tiling::AnimDir
+                ));
+                let variants = match object.direction {
+                    Direction::Up if directional => vec![Variant::Up()],
+                    Direction::Left if directional => vec![Variant::Left()],
+                    Direction::Down if directional => vec![Variant::Down()],
+                    _ => Vec::new()
+                };
+                objects.insert(Position { x, y, z, t: 0 }, RawTile {
+                    name: &object.name,
+                    world: None,
+                    tag: None,
+                    variants,
+                    span: Span::new(&object.name, 0, object.name.len())
+                        .expect("span should cover the whole name")
+                });
+            }
+        }
+        let mut flags = HashMap::new();
+        if let Some(palette) = &self.palette {
+            flags.insert(FlagName::Palette, Flag::Palette(PaletteName { world: None, name: palette.clone() }));
+        }
+        RawScene {
+            map: ObjectMap {
+                width: self.width,
+                height: self.height,
+                length: 1,
+                objects
+            },
+            flags
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::io;
+use displaydoc::Display;
+use thiserror::Error;
+
+/// A level from Baba Is You.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Level {
+    /// The name of the level.
+    pub name: String,
+    /// The name of the palette that the level uses, without its extension.
+    pub palette: Option<String>,
+    /// The width of the level, in tiles.
+    pub width: usize,
+    /// The height of the level, in tiles.
+    pub height: usize,
+    /// Every object in the level.
+    pub objects: Vec<LevelObject>,
+    /// Any objects that couldn't be resolved to a tile in the database.
+    pub unknown: Vec<UnknownObject>
+}
+
+/// A single object in a level.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LevelObject {
+    /// The x position of this object.
+    pub x: usize,
+    /// The y position of this object.
+    pub y: usize,
+    /// The index of the map layer in the level file that this object is in.
+    pub map_layer: usize,
+    /// The name of the tile this object resolved to.
+    pub name: String,
+    /// The direction this object is facing.
+    pub direction: Direction
+}
+
+/// An object in a level that couldn't be resolved to a tile.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct UnknownObject {
+    /// The x position of this object.
+    pub x: usize,
+    /// The y position of this object.
+    pub y: usize,
+    /// The raw tile index stored in the level file.
+    pub id: u16
+}
+
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[allow(missing_docs)]
+/// A direction that an object in a level can face.
+pub enum Direction {
+    #[default]
+    Right = 0,
+    Up = 1,
+    Left = 2,
+    Down = 3
+}
+
+impl TryFrom<u8> for Direction {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok( match value {
+            0 => Direction::Right,
+            1 => Direction::Up,
+            2 => Direction::Left,
+            3 => Direction::Down,
+            _ => return Err(value)
+        } )
+    }
+}
+
+#[derive(Debug, Display, Error)]
+/// Something went wrong while reading a level.
+pub enum LevelError {
+    #[displaydoc("IO error: {0}")]
+    /// Error opening or decompressing a file
+    IoError(#[from] io::Error),
+    #[displaydoc("expected {0} in level file at byte {1}")]
+    /// The level file didn't have an expected header
+    MissingHeader(&'static str, usize),
+    #[displaydoc("level file ended unexpectedly while reading {1} bytes at byte {0}")]
+    /// The level file ended unexpectedly, while reading a number of bytes from a position
+    UnexpectedEof(usize, usize),
+    #[displaydoc("map layer {0} has {1} tiles, but its size is {2}x{3}")]
+    /// A map layer's tile data didn't match its size
+    WrongLayerSize(usize, usize, usize, usize)
+}
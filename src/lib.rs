@@ -8,5 +8,6 @@ pub mod database;
 pub mod arguments;
 pub mod solidify;
 pub mod renderer;
+pub mod levels;
 
 // TODO: Re-exports
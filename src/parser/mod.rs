@@ -56,6 +56,7 @@ mod scene {
                 Rule::var_arg => "a list of arguments for a variant",
                 Rule::value | Rule::blacklist | Rule::ws => "<internal token>",
                 Rule::text | Rule::glyph | Rule::tag => "a tile prefix",
+                Rule::world => "a world name",
                 Rule::tile_name => "a tile name",
                 Rule::EOI => "the end of the input"
             })
@@ -63,7 +64,7 @@ mod scene {
     }
 }
 pub(crate) use scene::Rule;
-use crate::arguments::{Variant, ArgumentError, VariantName};
+use crate::arguments::{Variant, ArgumentError, VariantName, VariantRegistry};
 
 /// Formats a pest error for better readability.
 fn handle_error(error: Error<Rule>) -> Error<Rule> {
@@ -129,8 +130,20 @@ fn unescape(string: &str) -> Cow<str> {
 ///
 /// # Errors
 /// Errors if the scene fails to parse.
-#[allow(clippy::result_large_err, clippy::missing_panics_doc, clippy::too_many_lines)]
+#[allow(clippy::result_large_err)]
 pub fn parse(scene: &str) -> Result<RawScene, Error<Rule>> {
+    parse_with_variants(scene, &VariantRegistry::new())
+}
+
+/// Parses a raw scene, with custom variants alongside the built-in ones.
+///
+/// # Errors
+/// Errors if the scene fails to parse.
+#[allow(clippy::result_large_err, clippy::missing_panics_doc, clippy::too_many_lines)]
+pub fn parse_with_variants<'scene>(
+    scene: &'scene str,
+    registry: &VariantRegistry
+) -> Result<RawScene<'scene>, Error<Rule>> {
     // I'll be perfectly honest here.
     // Using pest here is overkill.
     // But, I like using it, so I'm using it.
@@ -160,7 +173,7 @@ pub fn parse(scene: &str) -> Result<RawScene, Error<Rule>> {
             );
             let Ok(identifier) = identifier else {return Some(Err(identifier.unwrap_err()))};
             let flag = Flag::parse(identifier, arg_strings).map_err(|err| {
-                let ArgumentError::InvalidArgument("flag", idx, err) = err 
+                let ArgumentError::InvalidArgument("Flag", idx, err) = err 
                     else {unreachable!("invalid flag should be the only error passed back here")};
                 let span = arg_spans.nth(idx).unwrap_or(name_pair.as_span());
                 Error::new_from_span(
@@ -233,10 +246,16 @@ pub fn parse(scene: &str) -> Result<RawScene, Error<Rule>> {
                 Rule::glyph => TileTag::Glyph,
                 _ => unreachable!()
             });
-            let name = parts.next().unwrap();
+            let mut name = parts.next().unwrap();
+            // Check for a world namespace
+            let mut world = None;
+            if name.as_rule() == Rule::world {
+                world = Some(name.as_str()).filter(|world| !world.is_empty());
+                name = parts.next().unwrap();
+            }
 
             // Parse the tile
-            let parsed = parse_tile(last_tile, tag, &name, variants);
+            let parsed = parse_tile(last_tile, tag, world, &name, variants, registry);
             let Ok(parsed) = parsed else {
                 let err = parsed.unwrap_err();
                 return Some(Err(err));
@@ -270,15 +289,20 @@ pub fn parse(scene: &str) -> Result<RawScene, Error<Rule>> {
 fn parse_tile<'scene, N: Num>(
     last_tile: &mut Option<(Position<N>, RawTile<'scene>)>,
     tag: Option<TileTag>,
+    mut world: Option<&'scene str>,
     name: &Pair<'scene, Rule>,
-    variants: Pair<'scene, Rule>
+    variants: Pair<'scene, Rule>,
+    registry: &VariantRegistry
 ) -> Result<Option<RawTile<'scene>>, Error<Rule>> {
     let mut new_tile = false;
 
     let name_string = match name.as_str() {
-        // Implicitly empty, fill with last tile
-        "" if last_tile.is_some() =>
-            last_tile.as_ref().unwrap().1.name,
+        // Implicitly empty, fill with last tile, keeping any world given explicitly
+        "" if last_tile.is_some() => {
+            let last = &last_tile.as_ref().unwrap().1;
+            world = world.or(last.world);
+            last.name
+        },
         // Explicitly empty, clear last and return Some(None)
         "." | "" => {
             *last_tile = None;
@@ -303,6 +327,11 @@ fn parse_tile<'scene, N: Num>(
 
         if let Some(var) = Variant::collapse_alias(name) {
             Ok(var)
+        } else if let Some(var) = registry.parse(name, arg_strings.clone()) {
+            var.map_err(|err| Error::new_from_span(
+                ErrorVariant::CustomError { message: format!("failed to parse variant: {err}") },
+                name_pair.as_span()
+            ))
         } else {
             let identifier = VariantName::from_alias(name).ok_or_else(||
                 Error::new_from_span(
@@ -313,7 +342,7 @@ fn parse_tile<'scene, N: Num>(
             Variant::parse(
                 identifier, arg_strings
             ).map_err(|err| {
-                let ArgumentError::InvalidArgument("variant", idx, err) = err 
+                let ArgumentError::InvalidArgument("Variant", idx, err) = err 
                     else {unreachable!("invalid argument should be the only error passed back here")};
                 let span = arg_spans.nth(idx).unwrap_or(name_pair.as_span());
                 Error::new_from_span(
@@ -330,5 +359,5 @@ fn parse_tile<'scene, N: Num>(
         variants = last_tile.as_ref().unwrap().1.variants.clone();
     }
 
-    Ok(Some(RawTile::<'scene> {name: name_string, tag, variants, span: name.as_span()}))
+    Ok(Some(RawTile::<'scene> {name: name_string, world, tag, variants, span: name.as_span()}))
 }
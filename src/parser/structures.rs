@@ -22,6 +22,8 @@ pub struct RawScene<'scene> {
 pub struct RawTile<'scene> {
     /// The tile's name.
     pub name: &'scene str,
+    /// The world the tile was namespaced to, if any.
+    pub world: Option<&'scene str>,
     /// The tag the tile may have.
     pub tag: Option<TileTag>,
     /// The tile's variants.
@@ -0,0 +1,98 @@
+//! Handles compositing the sprites of a rendered scene into images.
+
+use image::{Rgba, RgbaImage};
+
+use crate::arguments::BlendMode;
+use super::structures::{RenderedScene, SceneFrame, Sprite};
+
+impl BlendMode {
+    /// Blends a single color channel onto another, both from 0 to 1.
+    fn blend(self, source: f32, backdrop: f32) -> f32 {
+        match self {
+            BlendMode::Normal => source,
+            BlendMode::Add => (source + backdrop).min(1.0),
+            BlendMode::Multiply => source * backdrop,
+            BlendMode::Screen => 1.0 - (1.0 - source) * (1.0 - backdrop),
+            BlendMode::Subtract => (backdrop - source).max(0.0)
+        }
+    }
+}
+
+impl RenderedScene<'_> {
+    /// Draws every frame of this scene onto its background.
+    ///
+    /// Sprites are drawn from lowest to highest Z order,
+    /// each with its top-left corner at its position.
+    /// Scaled sprites are scaled around their anchor, so they stay centered on their tile.
+    #[must_use]
+    pub fn composite(&self) -> Vec<RgbaImage> {
+        self.frames.iter().map(|frame| self.composite_frame(frame)).collect()
+    }
+
+    /// Draws a single frame of this scene onto its background.
+    ///
+    /// See [`composite`](RenderedScene::composite) for details.
+    #[must_use]
+    pub fn composite_frame(&self, frame: &SceneFrame) -> RgbaImage {
+        #[allow(clippy::cast_possible_truncation)]
+        let mut canvas = RgbaImage::from_pixel(self.width as u32, self.height as u32, self.background);
+        let mut sprites = frame.sprites.iter().collect::<Vec<_>>();
+        sprites.sort_unstable_by_key(|sprite| sprite.z_order);
+        for sprite in sprites {
+            draw_sprite(&mut canvas, sprite);
+        }
+        canvas
+    }
+}
+
+/// Draws a sprite onto a canvas, scaled around its anchor.
+///
+/// Scaled sprites are sampled straight from the original image,
+/// so only the part of the sprite that lands on the canvas is ever computed.
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn draw_sprite(canvas: &mut RgbaImage, sprite: &Sprite) {
+    let (width, height) = sprite.image.dimensions();
+    if !sprite.size.is_finite() || sprite.size <= 0.0 || width == 0 || height == 0 {
+        return
+    }
+    let size = f64::from(sprite.size);
+    // Move the corner away from the anchor along with the rest of the sprite
+    let scale_from = |corner: isize, anchor: isize| anchor as f64 + (corner - anchor) as f64 * size;
+    let left = scale_from(sprite.position.0, sprite.anchor.0).round();
+    let top = scale_from(sprite.position.1, sprite.anchor.1).round();
+    let scaled_width = (f64::from(width) * size).round().max(1.0);
+    let scaled_height = (f64::from(height) * size).round().max(1.0);
+    // Only visit the pixels of the canvas that the sprite covers
+    let columns = left.max(0.0) as u32 .. (left + scaled_width).clamp(0.0, f64::from(canvas.width())) as u32;
+    let rows = top.max(0.0) as u32 .. (top + scaled_height).clamp(0.0, f64::from(canvas.height())) as u32;
+    for y in rows {
+        let source_y = ((f64::from(y) - top) * f64::from(height) / scaled_height) as u32;
+        for x in columns.clone() {
+            let source_x = ((f64::from(x) - left) * f64::from(width) / scaled_width) as u32;
+            let source = sprite.image.get_pixel(source_x.min(width - 1), source_y.min(height - 1));
+            blend_pixel(canvas.get_pixel_mut(x, y), *source, sprite);
+        }
+    }
+}
+
+/// Blends a single pixel of a sprite onto the canvas below it.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn blend_pixel(Rgba(backdrop): &mut Rgba<u8>, Rgba(source): Rgba<u8>, sprite: &Sprite) {
+    let source_alpha = f32::from(source[3]) / 255.0 * sprite.opacity;
+    if source_alpha <= 0.0 {
+        return
+    }
+    let backdrop_alpha = f32::from(backdrop[3]) / 255.0;
+    // Porter-Duff source-over, with the blend mode applied where both are opaque
+    let alpha = source_alpha + backdrop_alpha * (1.0 - source_alpha);
+    for channel in 0 .. 3 {
+        let src = f32::from(source[channel]) / 255.0;
+        let dst = f32::from(backdrop[channel]) / 255.0;
+        let blended = sprite.blend.blend(src, dst);
+        let color = source_alpha * (1.0 - backdrop_alpha) * src
+            + source_alpha * backdrop_alpha * blended
+            + (1.0 - source_alpha) * backdrop_alpha * dst;
+        backdrop[channel] = (color / alpha * 255.0).round().clamp(0.0, 255.0) as u8;
+    }
+    backdrop[3] = (alpha * 255.0).round() as u8;
+}
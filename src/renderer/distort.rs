@@ -0,0 +1,172 @@
+//! Handles variants that distort the shape of a sprite.
+
+use image::{imageops, GenericImageView, GrayImage, Rgba, Rgba32FImage, RgbaImage};
+use imageproc::{filter, noise};
+
+use crate::structures::Position;
+
+/// Adds transparent space around the sides of an image.
+pub(crate) fn pad(image: &RgbaImage, left: u8, top: u8, right: u8, bottom: u8) -> RgbaImage {
+    let mut padded = RgbaImage::new(
+        image.width() + u32::from(left) + u32::from(right),
+        image.height() + u32::from(top) + u32::from(bottom)
+    );
+    imageops::replace(&mut padded, image, i64::from(left), i64::from(top));
+    padded
+}
+
+/// The largest width or height that a variant can grow a sprite to, in pixels.
+pub(crate) const MAX_SIZE: u32 = 1024;
+
+/// Gets the size of an image after adding a margin to opposite sides,
+/// or `None` if it would be wider or taller than [`MAX_SIZE`].
+pub(crate) fn padded_size((width, height): (u32, u32), x: u32, y: u32) -> Option<(u32, u32)> {
+    let grow = |length: u32, margin: u32| margin.checked_mul(2)?
+        .checked_add(length)
+        .filter(|length| *length <= MAX_SIZE);
+    Some((grow(width, x)?, grow(height, y)?))
+}
+
+/// Adds the same amount of transparent space to opposite sides of an image.
+///
+/// Returns `None` if the image would grow past [`MAX_SIZE`].
+pub(crate) fn pad_evenly(image: &RgbaImage, x: u32, y: u32) -> Option<RgbaImage> {
+    let (width, height) = padded_size(image.dimensions(), x, y)?;
+    let mut padded = RgbaImage::new(width, height);
+    imageops::replace(&mut padded, image, i64::from(x), i64::from(y));
+    Some(padded)
+}
+
+/// Adds the same amount of empty space to every side of a grayscale image.
+///
+/// Returns `None` if the image would grow past [`MAX_SIZE`].
+pub(crate) fn pad_evenly_gray(image: &GrayImage, margin: u32) -> Option<GrayImage> {
+    let (width, height) = padded_size(image.dimensions(), margin, margin)?;
+    let mut padded = GrayImage::new(width, height);
+    imageops::replace(&mut padded, image, i64::from(margin), i64::from(margin));
+    Some(padded)
+}
+
+/// Displaces every row of an image sideways along a sine wave,
+/// or every column if `vertical` is set.
+///
+/// The image grows to fit the displaced rows, and `None` is returned if it would grow past [`MAX_SIZE`].
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub(crate) fn wave(image: &RgbaImage, amplitude: f32, wavelength: f32, vertical: bool) -> Option<RgbaImage> {
+    // Saturates instead of overflowing, which padding then rejects
+    let margin = amplitude.abs().ceil() as u32;
+    let (margin_x, margin_y) = if vertical { (0, margin) } else { (margin, 0) };
+    let padded = pad_evenly(image, margin_x, margin_y)?;
+    Some(RgbaImage::from_fn(padded.width(), padded.height(), |x, y| {
+        let (along, across) = if vertical { (x, y) } else { (y, x) };
+        let phase = along as f32 / wavelength * std::f32::consts::TAU;
+        let source = i64::from(across) - (phase.sin() * amplitude).round() as i64;
+        let (x, y) = if vertical { (i64::from(x), source) } else { (source, i64::from(y)) };
+        u32::try_from(x).ok().zip(u32::try_from(y).ok())
+            .and_then(|(x, y)| padded.get_pixel_checked(x, y))
+            .copied()
+            .unwrap_or(Rgba([0; 4]))
+    }))
+}
+
+/// Averages an image over square blocks of pixels.
+///
+/// Colors are weighted by their opacity, so transparent pixels don't darken a block.
+/// Blocks smaller than a pixel leave the image as it is.
+pub(crate) fn pixelate(image: &RgbaImage, block: u8) -> RgbaImage {
+    let block = u32::from(block.max(1));
+    let mut pixelated = image.clone();
+    for block_y in (0 .. image.height()).step_by(block as usize) {
+        for block_x in (0 .. image.width()).step_by(block as usize) {
+            let view = imageops::crop_imm(image, block_x, block_y, block, block);
+            let (width, height) = view.dimensions();
+            let (mut color, mut alpha) = ([0u32; 3], 0u32);
+            for (_, _, Rgba([r, g, b, a])) in view.pixels() {
+                for (total, channel) in color.iter_mut().zip([r, g, b]) {
+                    *total += u32::from(channel) * u32::from(a);
+                }
+                alpha += u32::from(a);
+            }
+            #[allow(clippy::cast_possible_truncation)]
+            let average = if alpha == 0 {
+                Rgba([0; 4])
+            } else {
+                let [r, g, b] = color.map(|total| (total / alpha) as u8);
+                Rgba([r, g, b, (alpha / (width * height)) as u8])
+            };
+            for y in block_y .. block_y + height {
+                for x in block_x .. block_x + width {
+                    pixelated.put_pixel(x, y, average);
+                }
+            }
+        }
+    }
+    pixelated
+}
+
+/// Multiplies the colors of an image by their opacity,
+/// so that averaging them doesn't bleed the color of transparent pixels into opaque ones.
+fn premultiply(image: &RgbaImage) -> Rgba32FImage {
+    Rgba32FImage::from_fn(image.width(), image.height(), |x, y| {
+        let Rgba([r, g, b, a]) = *image.get_pixel(x, y);
+        let alpha = f32::from(a) / f32::from(u8::MAX);
+        Rgba([f32::from(r) * alpha, f32::from(g) * alpha, f32::from(b) * alpha, f32::from(a)])
+    })
+}
+
+/// Undoes [`premultiply`], leaving fully transparent pixels transparent black.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn unpremultiply(image: &Rgba32FImage) -> RgbaImage {
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let Rgba([r, g, b, a]) = *image.get_pixel(x, y);
+        let alpha = a.round().clamp(0.0, 255.0);
+        if alpha == 0.0 {
+            return Rgba([0; 4]);
+        }
+        let channel = |value: f32| (value / a * 255.0).round().clamp(0.0, 255.0) as u8;
+        Rgba([channel(r), channel(g), channel(b), alpha as u8])
+    })
+}
+
+/// Blurs an image by averaging each pixel with its neighbors within a radius.
+///
+/// Colors are weighted by their opacity, so transparent pixels don't darken the edges.
+/// The image grows to fit the blur, and `None` is returned if it would grow past [`MAX_SIZE`].
+#[allow(clippy::cast_precision_loss)]
+pub(crate) fn box_blur(image: &RgbaImage, radius: u8) -> Option<RgbaImage> {
+    let radius = u32::from(radius);
+    let padded = pad_evenly(image, radius, radius)?;
+    let width = radius * 2 + 1;
+    let kernel = vec![1.0 / width as f32; width as usize];
+    Some(unpremultiply(&filter::separable_filter_equal(&premultiply(&padded), &kernel)))
+}
+
+/// Blurs an image with a gaussian kernel.
+///
+/// Like [`box_blur`], colors are weighted by their opacity.
+/// The image grows to fit the blur, and `None` is returned if it would grow past [`MAX_SIZE`].
+/// Blurs that aren't positive leave the image as it is.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub(crate) fn gaussian_blur(image: &RgbaImage, sigma: f32) -> Option<RgbaImage> {
+    if sigma.is_nan() || sigma <= 0.0 {
+        return Some(image.clone());
+    }
+    // Past three standard deviations, the kernel is close enough to zero
+    let radius = (sigma * 3.0).ceil() as u32;
+    let padded = premultiply(&pad_evenly(image, radius, radius)?);
+    Some(unpremultiply(&filter::gaussian_blur_f32(&padded, sigma)))
+}
+
+/// Adds random noise to the colors of an image, without changing its transparency.
+///
+/// The noise is seeded by the tile's position, so the same scene always renders the same way.
+pub(crate) fn noise(image: &RgbaImage, strength: f32, pos: Position<usize>) -> RgbaImage {
+    let seed = [pos.x, pos.y, pos.z].into_iter()
+        .fold(0u64, |seed, coord| seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ coord as u64);
+    let stddev = f64::from(strength) / 100.0 * 255.0;
+    let mut noisy = noise::gaussian_noise(image, 0.0, stddev, seed);
+    for (Rgba(noisy), Rgba(original)) in noisy.pixels_mut().zip(image.pixels()) {
+        noisy[3] = original[3];
+    }
+    noisy
+}
@@ -0,0 +1,161 @@
+//! Handles per-pixel color filters, applied to sprites after they're tinted.
+
+use image::{Rgba, RgbaImage};
+
+use crate::database::structures::Color;
+use super::structures::RawSprite;
+
+/// A filter over the colors of a sprite.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ColorFilter {
+    /// Rotates the hue by a number of degrees, so 0 and 360 leave it unchanged.
+    HueRotate(f32),
+    /// Inverts each color channel. This takes no amount.
+    Invert,
+    /// Removes a percentage of the color, so 0 leaves it unchanged and 100 makes it fully gray.
+    Grayscale(f32),
+    /// Scales the brightness by a percentage, so 100 leaves it unchanged and 0 makes it black.
+    Brightness(f32),
+    /// Scales the contrast by a percentage, so 100 leaves it unchanged and 0 makes it flat gray.
+    Contrast(f32),
+    /// Scales the saturation by a percentage, so 100 leaves it unchanged and 0 makes it fully gray.
+    Saturation(f32)
+}
+
+/// A gradient to tint a sprite with, instead of a single color.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Gradient {
+    /// Fades from one color to another along an angle, in degrees clockwise from the right.
+    Linear(Color, Color, f32),
+    /// Splits the sprite into equally tall rows of colors, from top to bottom.
+    Stripes(Vec<Color>)
+}
+
+impl Gradient {
+    /// Gets the tint of a pixel of an image with the given dimensions.
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn tint_at(&self, palette: &RgbaImage, (width, height): (u32, u32), (x, y): (u32, u32)) -> [f32; 3] {
+        match self {
+            Gradient::Linear(start, end, angle) => {
+                let (sin, cos) = angle.to_radians().sin_cos();
+                let (width, height) = (width as f32, height as f32);
+                // Project the pixel's center onto the gradient's direction
+                let extent = width * cos.abs() + height * sin.abs();
+                let along = (x as f32 + 0.5 - width / 2.0) * cos + (y as f32 + 0.5 - height / 2.0) * sin;
+                let progress = if extent > 0.0 { along / extent + 0.5 } else { 0.5 };
+                let (start, end) = (tint_of(*start, palette), tint_of(*end, palette));
+                [0, 1, 2].map(|i| start[i] + (end[i] - start[i]) * progress.clamp(0.0, 1.0))
+            },
+            Gradient::Stripes(colors) => {
+                let row = (y as usize * colors.len()) / height.max(1) as usize;
+                colors.get(row).map_or([1.0; 3], |color| tint_of(*color, palette))
+            }
+        }
+    }
+}
+
+/// Gets the color to multiply a sprite by, leaving it untinted if the color isn't in the palette.
+fn tint_of(color: Color, palette: &RgbaImage) -> [f32; 3] {
+    color.into_rgba(palette)
+        .map_or([1.0; 3], |Rgba([r, g, b, _])| [r, g, b].map(|channel| f32::from(channel) / 255.0))
+}
+
+/// The weights of each color channel to luminance.
+const LUMINANCE: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+impl ColorFilter {
+    /// Applies this filter to a single color, leaving its alpha untouched.
+    fn apply(self, color: [f32; 3]) -> [f32; 3] {
+        match self {
+            ColorFilter::HueRotate(degrees) => {
+                // Rotation around the gray axis, as specified by CSS
+                let (sin, cos) = degrees.to_radians().sin_cos();
+                let [lr, lg, lb] = LUMINANCE;
+                multiply([
+                    [
+                        lr + cos * (1.0 - lr) - sin * lr,
+                        lg - cos * lg - sin * lg,
+                        lb - cos * lb + sin * (1.0 - lb)
+                    ],
+                    [
+                        lr - cos * lr + sin * 0.143,
+                        lg + cos * (1.0 - lg) + sin * 0.140,
+                        lb - cos * lb - sin * 0.283
+                    ],
+                    [
+                        lr - cos * lr - sin * (1.0 - lr),
+                        lg - cos * lg + sin * lg,
+                        lb + cos * (1.0 - lb) + sin * lb
+                    ]
+                ], color)
+            },
+            ColorFilter::Invert => color.map(|channel| 1.0 - channel),
+            ColorFilter::Grayscale(amount) => {
+                let amount = amount / 100.0;
+                let luma = luminance(color);
+                color.map(|channel| channel + (luma - channel) * amount)
+            },
+            ColorFilter::Brightness(amount) => color.map(|channel| channel * amount / 100.0),
+            ColorFilter::Contrast(amount) =>
+                color.map(|channel| (channel - 0.5) * amount / 100.0 + 0.5),
+            ColorFilter::Saturation(amount) => {
+                let amount = amount / 100.0;
+                let luma = luminance(color);
+                color.map(|channel| luma + (channel - luma) * amount)
+            }
+        }
+    }
+}
+
+fn luminance([r, g, b]: [f32; 3]) -> f32 {
+    let [lr, lg, lb] = LUMINANCE;
+    r * lr + g * lg + b * lb
+}
+
+fn multiply(matrix: [[f32; 3]; 3], color: [f32; 3]) -> [f32; 3] {
+    matrix.map(|row| row.iter().zip(color).map(|(weight, channel)| weight * channel).sum())
+}
+
+impl RawSprite {
+    /// Tints this sprite with its color or gradient from the given palette,
+    /// then applies its color filters.
+    ///
+    /// Colors that aren't in the palette leave the sprite untinted.
+    /// Gradients tint the luminance of the sprite, rather than each channel.
+    pub(crate) fn tint(&mut self, palette: &RgbaImage) {
+        let filters = std::mem::take(&mut self.filters);
+        self.tint_with_filters(palette, &filters);
+    }
+
+    /// Tints this sprite right away, without applying its color filters,
+    /// so that anything drawn onto it afterwards keeps its own color.
+    ///
+    /// Any later tints are applied on top of this one.
+    pub(crate) fn tint_early(&mut self, palette: &RgbaImage) {
+        self.tint_with_filters(palette, &[]);
+        self.color = Color::RGB { r: u8::MAX, g: u8::MAX, b: u8::MAX };
+        self.gradient = None;
+    }
+
+    fn tint_with_filters(&mut self, palette: &RgbaImage, filters: &[ColorFilter]) {
+        let tint = tint_of(self.color, palette);
+        let dimensions = self.image.dimensions();
+        for (x, y, Rgba(pixel)) in self.image.enumerate_pixels_mut() {
+            let original = [0, 1, 2].map(|i| f32::from(pixel[i]) / 255.0);
+            let mut color = match &self.gradient {
+                None => [0, 1, 2].map(|i| original[i] * tint[i]),
+                Some(gradient) => {
+                    let luma = luminance(original);
+                    gradient.tint_at(palette, dimensions, (x, y)).map(|channel| channel * luma)
+                }
+            };
+            for filter in filters {
+                color = filter.apply(color);
+            }
+            for (channel, value) in pixel.iter_mut().zip(color) {
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                { *channel = (value.clamp(0.0, 1.0) * 255.0).round() as u8; }
+            }
+        }
+    }
+}
@@ -1,27 +1,32 @@
 #![cfg(feature = "rendering")]
 //! Handles rendering of scenes into sprites.
 
-use crate::{database::structures::Color, solidify::{SkeletalScene, TileSkeleton, TileSkeletonType}, structures::Position};
-use image::{DynamicImage, ImageBuffer, io::Reader as ImageReader, Luma, Rgba, RgbaImage};
+use crate::{database::{source::AssetSource, structures::{Color, TileData}, Database}, solidify::{SkeletalScene, TileSkeleton, TileSkeletonType}, structures::Position};
+use image::{imageops, DynamicImage, ImageBuffer, Luma, Rgba, RgbaImage};
 use pest::Span;
 use try_insert_ext::EntryInsertExt;
 use std::{
     collections::HashMap,
     io,
     path::{Path, PathBuf},
-    hash::BuildHasher
+    hash::BuildHasher,
+    time::Duration
 };
 use imageproc::definitions::Image;
 use imageproc::filter::Kernel;
 use itertools::Itertools;
 
+mod composite;
+mod distort;
+mod filters;
 mod structures;
-use crate::arguments::{Flag, FlagName, MetaKernel, Variant, VariantName};
-pub use structures::{RenderedScene, RenderingError};
+use crate::arguments::{Argument, Flag, FlagName, MetaKernel, PaletteName, TileName, Variant, VariantName};
+pub use structures::{RenderedScene, RenderingError, SceneFrame, Sprite};
 
-use self::structures::{RawSprite, Sprite};
+use self::filters::{ColorFilter, Gradient};
+use self::structures::RawSprite;
 
-/// Opens an image, potentially from a cache.
+/// Opens an image from an asset source, potentially from a cache.
 /// 
 /// # Note
 /// The pointer for a borrow would dangle or break aliasing rules if the cache is changed after returning,
@@ -29,6 +34,7 @@ use self::structures::{RawSprite, Sprite};
 ///
 /// (Or at least it probably could, but I'm not dealing with 50 different lifetimes at once.)
 fn open_cached<S: BuildHasher>(
+    assets: &dyn AssetSource,
     path: impl AsRef<Path>,
     cache: &mut Cache<S>,
 ) -> Result<RgbaImage, io::Error> {
@@ -36,8 +42,7 @@ fn open_cached<S: BuildHasher>(
     // Create a closure so we don't have to duplicate code
     let imgen = || {
         Ok::<_, io::Error>( 
-            ImageReader::open(path)?
-                .decode()
+            image::load_from_memory(&assets.read(path)?)
                 .map_err(io::Error::other)?
                 .into_rgba8() 
         )
@@ -52,44 +57,50 @@ fn open_cached<S: BuildHasher>(
     Ok(image)
 }
 
-fn resolve_palette<'s, 'br, 'c: 'br, S: BuildHasher>(
-    asset_path: PathBuf,
-    palette_path: PathBuf,
-    cache: &'br mut Cache<'s, S>,
-) -> Result<RgbaImage, RenderingError<'s>> {
-    // Resolve the palette
-    let mut glob_pattern = asset_path;
-    glob_pattern.push("*");
-    glob_pattern.push(palette_path.to_string_lossy().as_ref());
-    let glob_pattern = glob_pattern
-        .with_extension("png")
-        .to_string_lossy()
-        .to_string();
-    let found_palette_path = glob::glob(&glob_pattern)
-        .expect("glob pattern was invalid")
-        .find_map(Result::ok);
-    let Some(path) = found_palette_path else {
-        return Err(RenderingError::NoPalette(palette_path));
-    };
-    let reader =
-        ImageReader::open(&path).map_err(|err| RenderingError::FailedOpen(path.clone(), err))?;
-    let image = reader
-        .decode()
-        .map_err(|err| RenderingError::FailedDecode(path, err))?
-        .into_rgba8();
-    if let Some(cache) = cache {
-        Ok(cache.entry(palette_path).or_insert(image).clone())
-    } else {
-        Ok(image)
-    }
-}
-
+/// The pixel size of a single tile in the grid.
+const TILE_SIZE: usize = 24;
 
+/// How long each frame of a rendered scene is shown for.
+const FRAME_LENGTH: Duration = Duration::from_millis(200);
 
 type Cache<'c, S> = Option<&'c mut HashMap<PathBuf, RgbaImage, S>>;
 
+#[cfg(feature = "assets")]
+impl crate::database::ChangeSet {
+    /// Removes any cached sprites belonging to tiles in this change set,
+    /// so that they're reopened on the next render.
+    pub fn invalidate_cache<S: BuildHasher>(
+        &self,
+        cache: &mut HashMap<PathBuf, RgbaImage, S>
+    ) {
+        let stale = self.added.values()
+            .chain(self.removed.values())
+            .chain(self.modified.values().flat_map(|(old, new)| [old, new]))
+            .map(|tile| (Path::new(&tile.directory).join("sprites"), tile.sprite.as_str()))
+            .collect::<Vec<_>>();
+        cache.retain(|path, _| {
+            let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) else {
+                return true;
+            };
+            let file_name = file_name.to_string_lossy();
+            !stale.iter().any(|(sprite_dir, sprite)| {
+                // Sprite files are named {sprite}_{frame}_{wobble}.png
+                parent == sprite_dir && file_name
+                    .strip_prefix(sprite)
+                    .and_then(|rest| rest.strip_prefix('_'))
+                    .and_then(|rest| rest.strip_suffix(".png"))
+                    .and_then(|rest| rest.split_once('_'))
+                    .is_some_and(|(frame, wobble)|
+                        frame.parse::<u8>().is_ok() && wobble.parse::<u8>().is_ok()
+                    )
+            })
+        });
+    }
+}
+
 /// The main entrypoint in the renderer.
 ///
+/// Sprites are read from the asset source.
 /// If a cache can be passed in, then paths
 /// from the cache will be used instead of
 /// opening the file at the path.
@@ -103,22 +114,18 @@ type Cache<'c, S> = Option<&'c mut HashMap<PathBuf, RgbaImage, S>>;
 /// Errors if the scene fails to render. See [`RenderingError`] for details.
 pub fn render<'scene, 'cache, S: BuildHasher>(
     mut scene: SkeletalScene<'_, 'scene>,
-    asset_path: impl AsRef<Path>,
+    db: &Database,
+    assets: &dyn AssetSource,
     mut cache: Cache<'scene, S>,
 ) -> Result<RenderedScene<'cache>, RenderingError<'scene>> {
-    let asset_path = asset_path.as_ref();
-
     // Parse boolean flags
     let loops = scene.flags.remove(&FlagName::NoLoop).is_none();
 
-    // Get palette from assets
-    let palette_path: PathBuf = if let Some(flag) = scene.flags.remove(&FlagName::Palette) {
-        let Flag::Palette(path) = flag else {
-            unreachable!()
-        };
-        path.into()
-    } else {
-        "default".into()
+    // Get palette from the database
+    let PaletteName { world: palette_world, name: palette_name } = match scene.flags.remove(&FlagName::Palette) {
+        Some(Flag::Palette(palette)) => palette,
+        Some(_) => unreachable!(),
+        None => PaletteName { world: None, name: "default".to_string() }
     };
 
     let frame_indices = if let Some(flag) = scene.flags.remove(&FlagName::WobbleFrames) {
@@ -148,12 +155,9 @@ pub fn render<'scene, 'cache, S: BuildHasher>(
         frame_indices.len()
     };
 
-    let palette = cache.as_ref().and_then(|cache| cache.get(&palette_path));
-    // We use a match here to allow error propogation
-    let palette = match palette {
-        Some(v) => v.clone(),
-        None => resolve_palette(asset_path.to_path_buf(), palette_path, &mut cache)?,
-    };
+    let palette = &db.get_palette(palette_world.as_deref(), &palette_name)
+        .ok_or(RenderingError::NoPalette(palette_name))?
+        .image;
     // Get background color
     let background_color = scene
         .flags
@@ -166,17 +170,50 @@ pub fn render<'scene, 'cache, S: BuildHasher>(
                 .unwrap_or(
                     Color::Paletted { x: 0, y: 0 }, // Default background color
                 )
-                .into_rgba(&palette)
+                .into_rgba(palette)
         })
         .unwrap_or(Rgba([0; 4]));
     
     // Convert all tile skeletons to sprites
+    // Sorting before numbering keeps higher tiles drawn over lower ones
+    let context = SpriteContext {
+        db, assets, palette, frames_per_wobble,
+        frame_indices: &frame_indices,
+        length: scene.map.length
+    };
     let sprites = scene.map.objects.into_iter()
-        .map(|(pos, skel)| handle_sprite(
-            asset_path, &mut cache, pos, skel, frames_per_wobble, &frame_indices
-        ))
-        .collect::<Result<Vec<Sprite>, _>>()?;
-    todo!()
+        .sorted_unstable_by_key(|(pos, _)| (pos.t, pos.z, pos.y, pos.x))
+        .enumerate()
+        .map(|(z_order, (pos, skel))| Ok((pos.t, handle_sprite(&context, &mut cache, z_order, pos, skel)?)))
+        .collect::<Result<Vec<(usize, Sprite)>, _>>()?;
+
+    // Split the sprites up by the frame they're in
+    let mut frames = (0 .. scene.map.length.max(1))
+        .map(|_| SceneFrame { length: FRAME_LENGTH, sprites: Vec::new() })
+        .collect::<Vec<_>>();
+    for (t, sprite) in sprites {
+        frames[t].sprites.push(sprite);
+    }
+    Ok(RenderedScene {
+        background: background_color,
+        flags: scene.flags,
+        width: scene.map.width * TILE_SIZE,
+        height: scene.map.height * TILE_SIZE,
+        frames,
+        loops
+    })
+}
+
+/// Everything about a scene that's needed to render each of its sprites.
+#[derive(Clone, Copy)]
+struct SpriteContext<'a> {
+    db: &'a Database,
+    assets: &'a dyn AssetSource,
+    palette: &'a RgbaImage,
+    frames_per_wobble: usize,
+    frame_indices: &'a [u8],
+    /// The number of frames in the scene.
+    length: usize
 }
 
 /// Converts a single [`TileSkeleton`] into a [`Sprite`].
@@ -184,13 +221,13 @@ pub fn render<'scene, 'cache, S: BuildHasher>(
 /// # Errors
 /// Errors if conversion failed. 
 fn handle_sprite<'cache, 'scene, S: BuildHasher>(
-    asset_path: &Path,
+    context: &SpriteContext,
     cache: &mut Cache<'scene, S>,
+    z_order: usize,
     pos: Position<usize>,
-    mut skel: TileSkeleton<'_, 'scene>,
-    frames_per_wobble: usize,
-    frame_indices: &[u8]
+    mut skel: TileSkeleton<'_, 'scene>
 ) -> Result<Sprite<'cache>, RenderingError<'scene>> {
+    let SpriteContext { assets, palette, frames_per_wobble, frame_indices, .. } = *context;
     let time_index = pos.t;
     let frame_index = (time_index / frames_per_wobble) % frame_indices.len();
     // Due to doing % len, this is guaranteed to exist,
@@ -198,49 +235,69 @@ fn handle_sprite<'cache, 'scene, S: BuildHasher>(
     let wobble_frame = frame_indices[frame_index];
 
     let sprite = match skel.data {
-        TileSkeletonType::Existing(existing) => {
-            // Construct the sprite path
-            let mut sprite_path = asset_path.to_path_buf();
-            sprite_path.push(&existing.directory);
-            sprite_path.push("sprites");
-            // Create a fallback path to check if the current path doesn't exist
-            let mut fallback_path = sprite_path.clone();
-            sprite_path.push(format!("{}_{}_{}.png", existing.sprite, skel.animation_frame.0, wobble_frame));
-            fallback_path.push(format!("{}_{}_{}.png", existing.sprite, skel.animation_frame.1, wobble_frame));
-            match open_cached(&sprite_path, cache) {
-                // Found the default sprite - return it
-                Ok(v) => RawSprite {
-                    image: v,
-                    color: existing.color
-                },
-                // Couldn't find default sprite - try the fallback
-                Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                    let mut fallback = open_cached(fallback_path, cache)
-                        .map_err(|e| RenderingError::SpriteFailedOpen(skel.span, e))?;
-                    // Add the fallback to the cache in the original's stead
-                    if let Some(cache) = cache {
-                        fallback = cache.entry(sprite_path).or_insert(fallback).clone();
-                    }
-                    RawSprite {
-                        image: fallback,
-                        color: existing.color
-                    }
-                },
-                // Found it, but something else happened - reraise
-                Err(e) => return Err(RenderingError::SpriteFailedOpen(skel.span, e))
-            }
-        },
-        TileSkeletonType::Generative(ref gen) => generate_sprite(asset_path, cache, gen.to_string(), skel.span)?
+        TileSkeletonType::Existing(existing) => RawSprite::new(
+            open_tile_sprite(context, cache, existing, skel.animation_frame, wobble_frame, skel.span)?,
+            existing.color
+        ),
+        TileSkeletonType::Generative(ref gen) => generate_sprite(assets, cache, gen.to_string(), skel.span)?
     };
 
-    let sprite = handle_sprite_variants(asset_path, cache, &mut skel, sprite)?;
+    let mut sprite = handle_sprite_variants(context, cache, pos, wobble_frame, &mut skel, sprite)?;
+    sprite.tint(palette);
 
-    todo!()
+    #[allow(clippy::cast_possible_wrap)]
+    let position = ((pos.x * TILE_SIZE) as isize, (pos.y * TILE_SIZE) as isize);
+    #[allow(clippy::cast_possible_wrap)]
+    Ok(sprite.into_sprite(z_order, position, TILE_SIZE as isize))
+}
+
+/// Opens the sprite of a tile for an animation frame, with a fallback if the sprite for it doesn't exist.
+fn open_tile_sprite<'scene, S: BuildHasher>(
+    context: &SpriteContext,
+    cache: &mut Cache<S>,
+    tile: &TileData,
+    (frame, fallback): (u8, u8),
+    wobble_frame: u8,
+    span: Span<'scene>
+) -> Result<RgbaImage, RenderingError<'scene>> {
+    let SpriteContext { db, assets, .. } = *context;
+    if let Some(files) = db.sprite_files(&tile.directory, &tile.sprite) {
+        // The sprite index knows where the files are, so we don't need to try opening them
+        let path = [frame, fallback].into_iter()
+            .find_map(|frame| files.get(&(frame, wobble_frame)))
+            .ok_or_else(|| RenderingError::SpriteMissingFrame(
+                span, tile.sprite.clone(), frame, wobble_frame
+            ))?;
+        return open_cached(assets, path, cache)
+            .map_err(|e| RenderingError::SpriteFailedOpen(span, e));
+    }
+    // Construct the sprite path
+    let mut sprite_path = Database::sprite_directory(&tile.directory);
+    let mut fallback_path = sprite_path.clone();
+    // Create a fallback path to check if the current path doesn't exist
+    sprite_path.push(format!("{}_{}_{}.png", tile.sprite, frame, wobble_frame));
+    fallback_path.push(format!("{}_{}_{}.png", tile.sprite, fallback, wobble_frame));
+    match open_cached(assets, &sprite_path, cache) {
+        // Found the default sprite - return it
+        Ok(v) => Ok(v),
+        // Couldn't find default sprite - try the fallback
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let mut fallback = open_cached(assets, fallback_path, cache)
+                .map_err(|e| RenderingError::SpriteFailedOpen(span, e))?;
+            // Add the fallback to the cache in the original's stead
+            if let Some(cache) = cache {
+                fallback = cache.entry(sprite_path).or_insert(fallback).clone();
+            }
+            Ok(fallback)
+        },
+        // Found it, but something else happened - reraise
+        Err(e) => Err(RenderingError::SpriteFailedOpen(span, e))
+    }
 }
 
 /// Generates a sprite from a string.
 fn generate_sprite<'scene, S: BuildHasher>(
-    path: &Path,
+    assets: &dyn AssetSource,
     cache: &mut Cache<S>,
     genstring: String,
     span: Span<'scene>
@@ -264,65 +321,320 @@ macro_rules! variant_assert {
 ///
 /// # Errors
 /// Errors if a sprite variant fails to apply. See [`RenderingError`] for more details.
+#[allow(clippy::too_many_lines)]
 fn handle_sprite_variants<'scene, S: BuildHasher>(
-    path: &Path,
+    context: &SpriteContext,
     cache: &mut Cache<S>,
+    pos: Position<usize>,
+    wobble_frame: u8,
     skel: &mut TileSkeleton<'_, 'scene>,
     mut raw_sprite: RawSprite
 ) -> Result<RawSprite, RenderingError<'scene>> {
-    let variants = &mut skel.variants;
+    // Keyframed arguments go from their first keyframe on the first frame to their last on the last
+    #[allow(clippy::cast_precision_loss)]
+    let progress = if context.length > 1 { pos.t as f32 / (context.length - 1) as f32 } else { 0.0 };
+    let variants = std::mem::take(&mut skel.variants);
     let mut new_variants = Vec::new();
-    for variant in variants.drain(..) {
+    for variant in variants {
         match variant {
-            Variant::Meta(level, kernel, size) => {
-                // Unwrap defaults
-                let level = level.unwrap_or(1);
-                let kernel = kernel.unwrap_or(MetaKernel::Full);
-                let size = size.unwrap_or(1);
-                variant_assert!(Meta @ skel.span; size != 0; "meta size can't be zero");
-                // Extract alpha channel and turn it into an image we can convolve
-                let base = raw_sprite.image.pixels().map(
-                    |pix| {
-                        let alpha = pix.0[3];
-                        if level < 0 { !alpha } else { alpha }
+            Variant::Meta(level, kernel, size, color) => {
+                let level = level.unwrap_or_else(|| schema_default(VariantName::Meta, 0));
+                let kernel = kernel.unwrap_or_else(|| schema_default(VariantName::Meta, 1));
+                let size = size.unwrap_or_else(|| schema_default(VariantName::Meta, 2));
+                // Without a color, the outline is tinted along with the rest of the sprite
+                let color = match color {
+                    None => Rgba([u8::MAX; 4]),
+                    Some(color) => {
+                        let rgba = opaque_color(color, context.palette);
+                        variant_assert!(Meta @ skel.span; rgba.is_some(); "the outline color {color} isn't in the palette");
+                        raw_sprite.tint_early(context.palette);
+                        rgba.expect("we checked that this exists")
                     }
-                ).collect::<Vec<_>>();
-                let mut base_img = GrayImage::from_raw(
-                    raw_sprite.image.width(), raw_sprite.image.height(),
-                    base
-                ).expect("base image as u8s should fit in buffer of size w*h");
-                // Create a kernel
-                let kernel = kernel.of_size(size);
-                let proc_kernel = Kernel::new(kernel.as_ref(), kernel.width(), kernel.height());
-                // Convolve the base
-                for _ in 1 .. level.abs() {
-                    base_img = proc_kernel.filter(
-                        &base_img, |_, _| ()
-                    );
+                };
+                let image = meta(&raw_sprite.image, level, kernel, size, color)
+                    .ok_or_else(|| too_large(skel.span, VariantName::Meta))?;
+                raw_sprite.replace_centered(image);
+            },
+            Variant::Shadow(x, y, color, spread) => {
+                let color = color.unwrap_or_else(|| schema_default(VariantName::Shadow, 2));
+                let rgba = opaque_color(color, context.palette);
+                variant_assert!(Shadow @ skel.span; rgba.is_some(); "the shadow color {color} isn't in the palette");
+                raw_sprite.tint_early(context.palette);
+                let (image, (left, top)) = drop_shadow(
+                    &raw_sprite.image,
+                    (
+                        x.unwrap_or_else(|| schema_default(VariantName::Shadow, 0)),
+                        y.unwrap_or_else(|| schema_default(VariantName::Shadow, 1))
+                    ),
+                    spread.unwrap_or_else(|| schema_default(VariantName::Shadow, 3)),
+                    rgba.expect("we checked that this exists")
+                ).ok_or_else(|| too_large(skel.span, VariantName::Shadow))?;
+                raw_sprite.image = image;
+                raw_sprite.offset.0 += left;
+                raw_sprite.offset.1 += top;
+            },
+            Variant::Color(color) => {
+                raw_sprite.color = color;
+                raw_sprite.gradient = None;
+            },
+            Variant::Gradient(start, end, angle) => {
+                let angle = angle.unwrap_or_else(|| schema_default(VariantName::Gradient, 2)).at(progress);
+                raw_sprite.gradient = Some(Gradient::Linear(start, end, angle));
+            },
+            Variant::HorizontalGradient(start, end) =>
+                raw_sprite.gradient = Some(Gradient::Linear(start, end, 0.0)),
+            Variant::VerticalGradient(start, end) =>
+                raw_sprite.gradient = Some(Gradient::Linear(start, end, 90.0)),
+            Variant::Stripes(colors) => {
+                variant_assert!(Stripes @ skel.span; !colors.is_empty(); "stripes need at least one color");
+                raw_sprite.gradient = Some(Gradient::Stripes(colors));
+            },
+            Variant::Inactive() => {
+                if let TileSkeletonType::Existing(TileData { inactive_color: Some(color), .. }) = skel.data {
+                    raw_sprite.color = *color;
+                    raw_sprite.gradient = None;
                 }
-                let offset = base_img.width() - raw_sprite.image.width();
-                // Now that we've convolved, turn the base back to RGBA and apply
-                let applied = RgbaImage::from_fn(base_img.width(), base_img.height(), |x, y| {
-                    // Check the original pixel and apply it to the new image if needed
-                    let px @ Rgba([_, _, _, alpha]) = raw_sprite.image.get_pixel(x, y);
-                    if *alpha != 0 && (level % 2 == 1) && (level > 0) {
-                        *px
-                    } else if (*alpha != 0) ^ (level <= 0) {
-                        Rgba([0; 4])
-                    } else {
-                        let Luma([px]) = base_img.get_pixel(x, y);
-                        Rgba([*px; 4])
-                    }
-                });
-                raw_sprite.image = applied;
             },
+            Variant::FlipX() => imageops::flip_horizontal_in_place(&mut raw_sprite.image),
+            Variant::FlipY() => imageops::flip_vertical_in_place(&mut raw_sprite.image),
+            Variant::Rotate(degrees) =>
+                raw_sprite.replace_centered(rotate_nearest(&raw_sprite.image, degrees.at(progress))),
+            Variant::Scale(scale) => {
+                raw_sprite.size *= scale.at(progress);
+                // Each scale is bounded by its schema, but they can still stack up
+                let limit = VariantName::Scale.data().arguments[0].max.unwrap_or(f64::INFINITY);
+                variant_assert!(
+                    Scale @ skel.span; f64::from(raw_sprite.size) <= limit;
+                    "scales can multiply to at most {limit}, but these multiply to {}", raw_sprite.size
+                );
+            },
+            Variant::Hue(degrees) => raw_sprite.filters.push(ColorFilter::HueRotate(degrees.at(progress))),
+            Variant::Invert() => raw_sprite.filters.push(ColorFilter::Invert),
+            Variant::Grayscale(amount) => {
+                let amount = amount.unwrap_or_else(|| schema_default(VariantName::Grayscale, 0));
+                raw_sprite.filters.push(ColorFilter::Grayscale(amount.at(progress)));
+            },
+            Variant::Brightness(amount) => raw_sprite.filters.push(ColorFilter::Brightness(amount.at(progress))),
+            Variant::Contrast(amount) => raw_sprite.filters.push(ColorFilter::Contrast(amount.at(progress))),
+            Variant::Saturation(amount) => raw_sprite.filters.push(ColorFilter::Saturation(amount.at(progress))),
+            Variant::Opacity(opacity) => raw_sprite.opacity *= opacity.at(progress) / 100.0,
+            Variant::Blend(mode) => raw_sprite.blend = mode,
+            Variant::Wave(ref amplitude, ref wavelength) | Variant::VerticalWave(ref amplitude, ref wavelength) => {
+                let vertical = matches!(variant, Variant::VerticalWave(..));
+                let (amplitude, wavelength) = (amplitude.at(progress), wavelength.at(progress));
+                let name = if vertical { VariantName::VerticalWave } else { VariantName::Wave };
+                let image = distort::wave(&raw_sprite.image, amplitude, wavelength, vertical)
+                    .ok_or_else(|| too_large(skel.span, name))?;
+                raw_sprite.replace_centered(image);
+            },
+            Variant::Pixelate(block) => raw_sprite.image = distort::pixelate(&raw_sprite.image, block),
+            Variant::Blur(sigma) => {
+                let image = distort::gaussian_blur(&raw_sprite.image, sigma.at(progress))
+                    .ok_or_else(|| too_large(skel.span, VariantName::Blur))?;
+                raw_sprite.replace_centered(image);
+            },
+            Variant::BoxBlur(radius) => {
+                let image = distort::box_blur(&raw_sprite.image, radius)
+                    .ok_or_else(|| too_large(skel.span, VariantName::BoxBlur))?;
+                raw_sprite.replace_centered(image);
+            },
+            Variant::Noise(strength) => {
+                raw_sprite.image = distort::noise(&raw_sprite.image, strength.at(progress), pos);
+            },
+            Variant::Crop(x, y, width, height) => {
+                let (image_width, image_height) = raw_sprite.image.dimensions();
+                variant_assert!(
+                    Crop @ skel.span;
+                    width != 0 && height != 0
+                        && u32::from(x) + u32::from(width) <= image_width
+                        && u32::from(y) + u32::from(height) <= image_height;
+                    "crop must be a non-empty rectangle within the {image_width}x{image_height} sprite"
+                );
+                raw_sprite.image = imageops::crop_imm(
+                    &raw_sprite.image, x.into(), y.into(), width.into(), height.into()
+                ).to_image();
+                raw_sprite.offset.0 += isize::from(x);
+                raw_sprite.offset.1 += isize::from(y);
+            },
+            Variant::Pad(left, top, right, bottom) => {
+                raw_sprite.image = distort::pad(&raw_sprite.image, left, top, right, bottom);
+                raw_sprite.offset.0 -= isize::from(left);
+                raw_sprite.offset.1 -= isize::from(top);
+            },
+            Variant::Mask(TileName { ref world, ref name }) => {
+                let (db, world) = (context.db, world.as_deref());
+                let Some(tile) = db.get_tile(world, name)
+                    .or_else(|| db.get_tile(world, &db.get_alias(name)?.name)) else {
+                    let name = world.map_or_else(|| name.clone(), |world| format!("{world}/{name}"));
+                    return Err(RenderingError::SpriteInvalidVariant(
+                        skel.span, VariantName::Mask, format!("there's no tile named \"{name}\" to mask with")
+                    ))
+                };
+                let mask = open_tile_sprite(context, cache, tile, (0, 0), wobble_frame, skel.span)?;
+                apply_mask(&mut raw_sprite, &mask);
+            },
+            Variant::Custom(custom) => custom.apply(&mut raw_sprite.image).map_err(
+                |err| RenderingError::SpriteInvalidCustomVariant(skel.span, custom.data().name.clone(), err)
+            )?,
             others => new_variants.push(others)
         }
     }
-    todo!()
+    // Hand back anything that doesn't change the sprite
+    skel.variants = new_variants;
+    Ok(raw_sprite)
+}
+
+
+/// Parses the default of a variant's argument from its schema,
+/// so that the default is only written down in one place.
+fn schema_default<A: Argument>(name: VariantName, index: usize) -> A {
+    let default = name.data().arguments[index].default.expect("argument should have a default");
+    A::parse(std::iter::once(default)).expect("argument default should parse")
 }
 
+/// The error for a variant that would grow a sprite past [`distort::MAX_SIZE`].
+fn too_large(span: Span<'_>, name: VariantName) -> RenderingError<'_> {
+    RenderingError::SpriteInvalidVariant(
+        span, name, format!("the sprite would grow past {0}x{0} pixels", distort::MAX_SIZE)
+    )
+}
+
+/// Gets a color from the palette, without any transparency.
+fn opaque_color(color: Color, palette: &RgbaImage) -> Option<Rgba<u8>> {
+    let Rgba([r, g, b, _]) = color.into_rgba(palette)?;
+    Some(Rgba([r, g, b, u8::MAX]))
+}
 
+/// Multiplies the alpha of a sprite by the alpha of a mask,
+/// lined up by where they'd be in the grid.
+fn apply_mask(raw_sprite: &mut RawSprite, mask: &RgbaImage) {
+    let (offset_x, offset_y) = raw_sprite.offset;
+    for (x, y, Rgba(pixel)) in raw_sprite.image.enumerate_pixels_mut() {
+        #[allow(clippy::cast_possible_wrap)]
+        let (mask_x, mask_y) = (x as isize + offset_x, y as isize + offset_y);
+        let mask_alpha = u32::try_from(mask_x).ok().zip(u32::try_from(mask_y).ok())
+            .and_then(|(x, y)| mask.get_pixel_checked(x, y))
+            .map_or(0, |Rgba([_, _, _, alpha])| u16::from(*alpha));
+        #[allow(clippy::cast_possible_truncation)]
+        { pixel[3] = (u16::from(pixel[3]) * mask_alpha / 255) as u8; }
+    }
+}
+
+/// Rotates an image clockwise by an arbitrary angle in degrees, using nearest-neighbor sampling.
+///
+/// The returned image is large enough to fit the entire rotated image.
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn rotate_nearest(image: &RgbaImage, degrees: f32) -> RgbaImage {
+    // Right angles don't need resampling, and are common enough to special-case
+    match degrees.rem_euclid(360.0) {
+        0.0 => return image.clone(),
+        90.0 => return imageops::rotate90(image),
+        180.0 => return imageops::rotate180(image),
+        270.0 => return imageops::rotate270(image),
+        _ => ()
+    }
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    // Bounding box of the rotated image
+    let new_width = (width * cos.abs() + height * sin.abs()).round().max(1.0);
+    let new_height = (width * sin.abs() + height * cos.abs()).round().max(1.0);
+    RgbaImage::from_fn(new_width as u32, new_height as u32, |x, y| {
+        // Map each destination pixel's center back onto the source image
+        let dx = x as f32 + 0.5 - new_width / 2.0;
+        let dy = y as f32 + 0.5 - new_height / 2.0;
+        let sx = (dx * cos + dy * sin + width / 2.0).floor();
+        let sy = (dy * cos - dx * sin + height / 2.0).floor();
+        if (0.0 .. width).contains(&sx) && (0.0 .. height).contains(&sy) {
+            *image.get_pixel(sx as u32, sy as u32)
+        } else {
+            Rgba([0; 4])
+        }
+    })
+}
+
+
+/// Outlines an image by convolving its alpha channel with a kernel,
+/// drawing the outline in the given color.
+///
+/// Each level outlines the previous outline, and odd positive levels keep the original image.
+/// Negative levels outline the inside of the image instead.
+/// Positive levels grow the image to fit the outline,
+/// and `None` is returned if it would grow past [`distort::MAX_SIZE`].
+fn meta(image: &RgbaImage, level: i8, kernel: MetaKernel, size: u8, color: Rgba<u8>) -> Option<RgbaImage> {
+    let margin = if level > 0 { u32::from(level.unsigned_abs()).checked_mul(u32::from(size))? } else { 0 };
+    let image = distort::pad_evenly(image, margin, margin)?;
+    // Extract alpha channel and turn it into an image we can convolve
+    let mut outline = GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        let opaque = image.get_pixel(x, y).0[3] != 0;
+        Luma([if opaque ^ (level < 0) { u8::MAX } else { 0 }])
+    });
+    let kernel = kernel.of_size(size);
+    for _ in 0 .. level.unsigned_abs() {
+        let dilated = dilate(&outline, &kernel);
+        // Only keep what the kernel added
+        outline = GrayImage::from_fn(outline.width(), outline.height(), |x, y| {
+            let added = dilated.get_pixel(x, y).0[0] != 0 && outline.get_pixel(x, y).0[0] == 0;
+            Luma([if added { u8::MAX } else { 0 }])
+        });
+    }
+    // Now that we've convolved, turn the outline back to RGBA and apply
+    Some(RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let px = image.get_pixel(x, y);
+        if px.0[3] != 0 && level > 0 && level % 2 == 1 {
+            *px
+        } else if outline.get_pixel(x, y).0[0] != 0 {
+            color
+        } else {
+            Rgba([0; 4])
+        }
+    }))
+}
+
+/// Draws a colored silhouette of an image beneath it, offset by some amount,
+/// and optionally grown by a kernel beforehand.
+///
+/// Returns the new image, along with how far its top-left corner moved,
+/// or `None` if it would grow past [`distort::MAX_SIZE`].
+#[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+fn drop_shadow(image: &RgbaImage, (x, y): (isize, isize), spread: u8, color: Rgba<u8>) -> Option<(RgbaImage, (isize, isize))> {
+    let mut silhouette = GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        Luma([if image.get_pixel(x, y).0[3] != 0 { u8::MAX } else { 0 }])
+    });
+    if spread != 0 {
+        let padded = distort::pad_evenly_gray(&silhouette, spread.into())?;
+        let mut dilated = dilate(&padded, &MetaKernel::Edge.of_size(spread));
+        // The kernel skips its center, so keep what was already there
+        for (Luma([dilated]), Luma([original])) in dilated.pixels_mut().zip(padded.pixels()) {
+            *dilated |= original;
+        }
+        silhouette = dilated;
+    }
+    let spread = isize::from(spread);
+    let (width, height) = (isize::try_from(image.width()).ok()?, isize::try_from(image.height()).ok()?);
+    // Find the bounds of both images, relative to the original
+    let left = x.min(0).checked_sub(spread)?;
+    let top = y.min(0).checked_sub(spread)?;
+    let right = width.max(width.checked_add(x)?.checked_add(spread)?);
+    let bottom = height.max(height.checked_add(y)?.checked_add(spread)?);
+    let shadowed_width = u32::try_from(right.checked_sub(left)?).ok().filter(|width| *width <= distort::MAX_SIZE)?;
+    let shadowed_height = u32::try_from(bottom.checked_sub(top)?).ok().filter(|height| *height <= distort::MAX_SIZE)?;
+    let mut shadowed = RgbaImage::from_fn(shadowed_width, shadowed_height, |px, py| {
+        let (sx, sy) = (px as isize + left - x + spread, py as isize + top - y + spread);
+        let in_shadow = u32::try_from(sx).ok().zip(u32::try_from(sy).ok())
+            .and_then(|(sx, sy)| silhouette.get_pixel_checked(sx, sy))
+            .is_some_and(|Luma([alpha])| *alpha != 0);
+        if in_shadow { color } else { Rgba([0; 4]) }
+    });
+    imageops::overlay(&mut shadowed, image, -left as i64, -top as i64);
+    Some((shadowed, (left, top)))
+}
+
+/// Grows an alpha mask by a kernel, marking every pixel the kernel can reach from an opaque one.
+fn dilate(mask: &GrayImage, kernel: &GrayImage) -> GrayImage {
+    let weights = kernel.pixels().map(|Luma([weight])| u32::from(*weight != 0)).collect::<Vec<_>>();
+    let kernel = Kernel::new(&weights, kernel.width(), kernel.height());
+    kernel.filter(mask, |out: &mut u8, sum: u32| *out = if sum != 0 { u8::MAX } else { 0 })
+}
 
 type GrayImage = Image<Luma<u8>>;
 
@@ -330,6 +642,7 @@ impl MetaKernel {
     pub(crate) fn of_size(self, size: u8) -> GrayImage {
         let center = u32::from(size);
         let width = (center * 2) + 1;
+        let last = width - 1;
         GrayImage::from_fn(width, width, |x, y| {
             let mut draw_pixel: bool = true;
             match self {
@@ -340,15 +653,15 @@ impl MetaKernel {
                 MetaKernel::Edge => {
                     if x == center && y == center { draw_pixel = false }
                     if x == 0 && y == 0 { draw_pixel = false }
-                    if x == 0 && y == width { draw_pixel = false }
-                    if x == width && y == 0 { draw_pixel = false }
-                    if x == width && y == width { draw_pixel = false }
+                    if x == 0 && y == last { draw_pixel = false }
+                    if x == last && y == 0 { draw_pixel = false }
+                    if x == last && y == last { draw_pixel = false }
                 },
                 // Round top, sharp bottom
                 MetaKernel::Unit => {
                     if x == center && y == center { draw_pixel = false }
                     if x == 0 && y == 0 { draw_pixel = false }
-                    if x == width && y == 0 { draw_pixel = false }
+                    if x == last && y == 0 { draw_pixel = false }
                 },
             }
             if draw_pixel {Luma([u8::MAX])} else {Luma([0])}
@@ -10,9 +10,11 @@ use std::time::Duration;
 use image::{ImageError, Rgba, RgbaImage};
 use pest::{error::ErrorVariant, Span};
 use thiserror::Error;
-use crate::{arguments::{Flag, FlagName}, database::structures::Color};
+use crate::{arguments::{BlendMode, Flag, FlagName}, database::structures::Color};
 use crate::arguments::VariantName;
 
+use super::filters::{ColorFilter, Gradient};
+
 
 /// A rendered scene, ready to be passed back to the renderer implementation.
 #[derive(Debug, Clone, PartialEq)]
@@ -52,8 +54,15 @@ pub struct Sprite<'cache> {
     pub z_order: usize,
     /// The pixel position of this sprite in the frame.
     pub position: (isize, isize),
+    /// The pixel position in the frame that this sprite is scaled around,
+    /// which is the center of its tile.
+    pub anchor: (isize, isize),
     /// The sprite's image.
-    pub image: Cow<'cache, RgbaImage>
+    pub image: Cow<'cache, RgbaImage>,
+    /// The opacity of this sprite, from 0 to 1.
+    pub opacity: f32,
+    /// How this sprite is blended onto the sprites below it.
+    pub blend: BlendMode
 }
 
 #[derive(Debug, Error)]
@@ -63,14 +72,18 @@ pub enum RenderingError<'scene> {
     SpriteFailedOpen(Span<'scene>, io::Error),
     /// The given tile doesn't exist.
     SpriteNoTile(Span<'scene>, String),
+    /// A tile's sprite doesn't have a file for the frame it needs.
+    SpriteMissingFrame(Span<'scene>, String, u8, u8),
     /// Couldn't find a palette.
     SpriteNoPalette(Span<'scene>, PathBuf),
     /// Failed to decode an image.
     SpriteFailedDecode(Span<'scene>, PathBuf, ImageError),
     /// A variant failed to compute.
     SpriteInvalidVariant(Span<'scene>, VariantName, String),
+    /// A custom variant failed to compute.
+    SpriteInvalidCustomVariant(Span<'scene>, String, String),
     /// Couldn't find a palette for the scene.
-    NoPalette(PathBuf),
+    NoPalette(String),
     /// Failed to open something that isn't a sprite.
     FailedOpen(PathBuf, io::Error),
     /// Failed to decode an image.
@@ -108,6 +121,11 @@ impl<'scene> Display for RenderingError<'scene> {
                     "couldn't open a sprite for this tile\n\
                     error: {err}"
                 ),
+            RenderingError::SpriteMissingFrame(span, sprite, frame, wobble) =>
+                spanned_err!(
+                    f, span,
+                    "the sprite \"{sprite}\" for this tile has no file for frame {frame}, wobble {wobble}"
+                ),
             RenderingError::SpriteNoPalette(span, pal_name) => 
                 spanned_err!(
                     f, span, 
@@ -124,8 +142,10 @@ impl<'scene> Display for RenderingError<'scene> {
                 ),
             RenderingError::SpriteInvalidVariant(span, name, err) =>
                 spanned_err!(f, span, "failed to apply variant {name}: {err}"),
-            RenderingError::NoPalette(path) =>
-                write!(f, "couldn't find a palette named {}", path.display()),
+            RenderingError::SpriteInvalidCustomVariant(span, name, err) =>
+                spanned_err!(f, span, "failed to apply variant {name}: {err}"),
+            RenderingError::NoPalette(name) =>
+                write!(f, "couldn't find a palette named {name}"),
             RenderingError::FailedOpen(path, err) =>
                 write!(f, "failed to open \"{}\": {err}", path.display()),
             RenderingError::FailedDecode(path, err) =>
@@ -136,8 +156,51 @@ impl<'scene> Display for RenderingError<'scene> {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) struct RawSprite {
     pub(crate) image: RgbaImage,
-    pub(crate) color: Color
+    pub(crate) color: Color,
+    /// A gradient to tint with instead of the color, if any.
+    pub(crate) gradient: Option<Gradient>,
+    /// How far the top-left corner of the image is from the tile's position in the grid.
+    pub(crate) offset: (isize, isize),
+    /// The size multiplier to pass on to the [`Sprite`].
+    pub(crate) size: f32,
+    /// Color filters to apply after tinting, in order.
+    pub(crate) filters: Vec<ColorFilter>,
+    /// The opacity to pass on to the [`Sprite`].
+    pub(crate) opacity: f32,
+    /// The blend mode to pass on to the [`Sprite`].
+    pub(crate) blend: BlendMode
+}
+
+impl RawSprite {
+    pub(crate) fn new(image: RgbaImage, color: Color) -> Self {
+        Self { image, color, gradient: None, offset: (0, 0), size: 1.0, filters: Vec::new(), opacity: 1.0, blend: BlendMode::Normal }
+    }
+
+    /// Replaces the image with one of a different size, keeping it centered on the same spot.
+    pub(crate) fn replace_centered(&mut self, image: RgbaImage) {
+        let grow = |old: u32, new: u32| (i64::from(new) - i64::from(old)) / 2;
+        let (width, height) = self.image.dimensions();
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            self.offset.0 -= grow(width, image.width()) as isize;
+            self.offset.1 -= grow(height, image.height()) as isize;
+        }
+        self.image = image;
+    }
+
+    /// Turns this into a [`Sprite`] for the tile whose top-left corner is at the given position.
+    pub(crate) fn into_sprite<'cache>(self, z_order: usize, position: (isize, isize), tile_size: isize) -> Sprite<'cache> {
+        Sprite {
+            size: self.size,
+            z_order,
+            position: (position.0 + self.offset.0, position.1 + self.offset.1),
+            anchor: (position.0 + tile_size / 2, position.1 + tile_size / 2),
+            image: Cow::Owned(self.image),
+            opacity: self.opacity,
+            blend: self.blend
+        }
+    }
 }
@@ -2,6 +2,7 @@
 
 
 use std::{borrow::Cow, collections::{BTreeMap, HashMap, HashSet}};
+use displaydoc::Display;
 use pest::Span;
 use rand::seq::SliceRandom;
 
@@ -98,6 +99,7 @@ impl<'scene> RawScene<'scene> {
         scene.map.length = self.map.length;
         // Construct the tiles
         let mut map = self.map;
+        let mut warnings = Vec::new();
         let name_map = map.objects.iter().map(|(pos, tile)| {
             // Transform the name into its canonical representation
             let name = match (tile.tag, &default) {
@@ -120,6 +122,32 @@ impl<'scene> RawScene<'scene> {
             let mut name = name.clone();
             let pos = *pos;
             let tile = map.objects.remove(&pos).expect("positions from name map should sync with positions in tile map");
+
+            'handle_2: {
+                if name == "2" {
+                    // Easter egg! Grab any character tile currently in the database.
+                    let characters = db.tiles.keys()
+                        .filter(|name| easter_egg_tiles.contains(*name))
+                        .collect::<Vec<_>>();
+                    let Some(chosen_name) = characters
+                        .choose(&mut rand::thread_rng())
+                    else {
+                        break 'handle_2
+                    };
+                    name = Cow::Borrowed(chosen_name.as_str());
+                }
+            }
+
+            // Fall back to any tile that has this name as an alias
+            let data = db.get_tile(tile.world, name.as_ref()).or_else(|| {
+                let alias = db.get_alias(name.as_ref())?;
+                let data = db.get_tile(tile.world, &alias.name)?;
+                if alias.deprecated {
+                    warnings.push(SolidifyWarning::DeprecatedName(tile.span, name.to_string(), alias.name.clone()));
+                }
+                Some(data)
+            });
+
             // Apply animation frame-level variants
             let mut anim_frame: Option<(u8, u8)> = None;
             let new_variants = tile.variants.into_iter().filter(
@@ -142,8 +170,16 @@ impl<'scene> RawScene<'scene> {
                             anim_frame = Some((sleep_frame, frame));
                         },
                         Variant::Animation(cycle) => {
-                            let mut frame = anim_frame.unwrap_or_default().0;
-                            frame += cycle;
+                            let frame = anim_frame.unwrap_or_default().0;
+                            let frame = match data {
+                                // Cycle within the frames that the tile actually has
+                                Some(TileData { frame_count: Some(count @ 1..), tiling, .. }) => {
+                                    let base = if *tiling == Tiling::Animated { 0 } else { frame - frame % 8 };
+                                    let cycled = (u16::from(frame - base) + u16::from(*cycle)) % u16::from(*count);
+                                    base + u8::try_from(cycled).expect("cycled frame should be less than the frame count")
+                                },
+                                _ => frame + cycle
+                            };
                             anim_frame = Some((frame, frame));
                         },
                         Variant::Tiling(directions) => {
@@ -169,22 +205,7 @@ impl<'scene> RawScene<'scene> {
                 }
             ).collect();
 
-            'handle_2: {
-                if name == "2" {
-                    // Easter egg! Grab any character tile currently in the database.
-                    let characters = db.tiles.keys()
-                        .filter(|name| easter_egg_tiles.contains(*name))
-                        .collect::<Vec<_>>();
-                    let Some(chosen_name) = characters
-                        .choose(&mut rand::thread_rng())
-                    else {
-                        break 'handle_2
-                    };
-                    name = Cow::Borrowed(chosen_name.as_str());
-                }
-            }
-
-            if let Some(data) = db.tiles.get(name.as_ref()) {
+            if let Some(data) = data {
                 if anim_frame.is_none() && data.tiling == Tiling::AutoTiled {
                     // Find the neighbors of this tile
                     let mut neighbors = TileNeighbors::empty();
@@ -217,6 +238,7 @@ impl<'scene> RawScene<'scene> {
                 })
             }
         }).collect();
+        scene.warnings = warnings;
         scene
     }
 }
@@ -253,7 +275,16 @@ pub struct SkeletalScene<'db, 'scene> {
     /// Whether any generated text objects default to letters.
     pub letters: bool,
     /// The attached flags of the scene.
-    pub flags: HashMap<FlagName, Flag>
+    pub flags: HashMap<FlagName, Flag>,
+    /// Any problems with the scene that didn't stop it from being solidified.
+    pub warnings: Vec<SolidifyWarning<'scene>>
+}
+
+/// A problem with a scene that doesn't stop it from being solidified.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+pub enum SolidifyWarning<'scene> {
+    /// `{1}` has been renamed to `{2}`, and the old name may stop working in the future
+    DeprecatedName(Span<'scene>, String, String)
 }
 
 
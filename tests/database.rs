@@ -1,17 +1,20 @@
 #![cfg(feature = "assets")]
 
 use std::{
-    collections::{BTreeSet, BTreeMap},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     path::PathBuf
 };
-use std::collections::HashMap;
 
 use chilly::database::{
-    structures::{Color, TileData, Tiling},
-    Database
+    source::MemorySource,
+    structures::{Color, TileAlias, TileData, Tiling, World},
+    AssetWatcher, Database, SharedDatabase, TileChange, TileConflict
 };
+use chilly::solidify::{SolidifyWarning, TileDefault, TileSkeletonType};
 
+use std::fs;
 use std::process::ExitCode;
+use std::time::{Duration, SystemTime};
 
 #[test]
 // Do this so the error message prints pretty
@@ -66,7 +69,8 @@ fn _main() -> Result<(), Box<dyn std::error::Error>> {
                 tile_index: Some((1, 0)),
                 grid_index: Some((0, 1)),
                 object_id: Some("object999".into()),
-                tags: BTreeSet::from(["tag1".into(), "tag2".into()])
+                tags: BTreeSet::from(["tag1".into(), "tag2".into()]),
+                ..Default::default()
             }),
             ("sample2".into(), TileData {
                 color: Color::Paletted { x: 3, y: 2 },
@@ -78,7 +82,20 @@ fn _main() -> Result<(), Box<dyn std::error::Error>> {
                 tile_index: Some((1, 1)),
                 grid_index: Some((0, 2)),
                 object_id: Some("object950".into()),
-                tags: BTreeSet::new()
+                ..Default::default()
+            }),
+            ("text_sample".into(), TileData {
+                color: Color::Paletted { x: 2, y: 2 },
+                inactive_color: Some(Color::Paletted { x: 2, y: 1 }),
+                sprite: "text_sample".into(),
+                directory: "vanilla".into(),
+                tiling: Tiling::None,
+                author: "Hempuli".into(),
+                layer: Some(20),
+                tile_index: Some((2, 0)),
+                grid_index: Some((1, 1)),
+                object_id: Some("object960".into()),
+                ..Default::default()
             }),
             ("editor_sample".into(), TileData {
                 color: Color::Paletted { x: 2, y: 3 },
@@ -90,7 +107,12 @@ fn _main() -> Result<(), Box<dyn std::error::Error>> {
                 tags: BTreeSet::from(["tag1".into(), "tag2".into(), "tag3".into()]),
                 ..Default::default()
             })
-        ])
+        ]),
+        worlds: HashMap::from([
+            ("sample".into(), World { name: "sample".into(), ..Default::default() }),
+            ("sample2".into(), World { name: "sample2".into(), ..Default::default() })
+        ]),
+        ..Default::default()
     };
 
     let mut database = Database::new();
@@ -98,9 +120,431 @@ fn _main() -> Result<(), Box<dyn std::error::Error>> {
     let testing_path = PathBuf::from(file!());
     let custom_assets = testing_path.with_file_name("assets");
     let vanilla_assets = testing_path.with_file_name("notbaba");
-    database.load_custom(custom_assets)?;
+    let conflicts = database.load_custom(custom_assets)?;
+    assert!(conflicts.is_empty());
     database.load_vanilla(vanilla_assets)?;
     assert_eq!(database, sample_db);
 
     Ok(())
 }
+
+#[test]
+fn world_conflicts() {
+    let conflict_assets = PathBuf::from(file!()).with_file_name("conflicts");
+
+    // Without any configuration, worlds take precedence alphabetically
+    let mut database = Database::new();
+    let conflicts = database.load_custom(&conflict_assets).expect("failed to load conflicting worlds");
+    assert_eq!(conflicts, vec![TileConflict {
+        name: "shared".into(),
+        world: "alpha".into(),
+        overridden: vec!["beta".into()]
+    }]);
+    assert_eq!(database.tiles["shared"].sprite, "shared_alpha");
+
+    // Explicit precedence wins over alphabetical order
+    let mut database = Database {
+        world_precedence: vec!["beta".into()],
+        ..Default::default()
+    };
+    let conflicts = database.load_custom(&conflict_assets).expect("failed to load conflicting worlds");
+    assert_eq!(conflicts[0].world, "beta");
+    assert_eq!(database.tiles["shared"].sprite, "shared_beta");
+
+    // Overridden tiles are still reachable through their world
+    let shadowed = database.get_tile(Some("alpha"), "shared").expect("overridden tile was lost");
+    assert_eq!(shadowed.sprite, "shared_alpha");
+    assert_eq!(database.get_tile(None, "shared").unwrap().sprite, "shared_beta");
+    assert!(database.get_tile(Some("beta"), "lonely").is_none());
+}
+
+#[test]
+fn vanilla_precedence() {
+    // Even a world that would sort after "vanilla" alphabetically overrides it
+    let mut source = MemorySource::new();
+    source.insert("zeta/sprites.toml", "[sample]\ncolor = [2, 2]\nsprite = \"sample_zeta\"\ntiling = -1\nauthor = \"zeta\"\n");
+    let mut database = Database::new();
+    database.load_vanilla(PathBuf::from(file!()).with_file_name("notbaba")).expect("failed to load vanilla assets");
+    let conflicts = database.load_custom_from(&source).expect("failed to load worlds");
+    assert_eq!(conflicts, vec![TileConflict {
+        name: "sample".into(),
+        world: "zeta".into(),
+        overridden: vec!["vanilla".into()]
+    }]);
+    assert_eq!(database.tiles["sample"].sprite, "sample_zeta");
+    assert_eq!(database.get_tile(Some("vanilla"), "sample").map(|tile| tile.directory.as_str()), Some("vanilla"));
+}
+
+#[test]
+fn hot_reload() {
+    // Work on a copy of the fixtures, since we're changing them
+    let fixture_path = PathBuf::from(file!()).with_file_name("conflicts");
+    let assets = std::env::temp_dir().join(format!("chilly-hot-reload-{}", std::process::id()));
+    for world in ["alpha", "beta"] {
+        fs::create_dir_all(assets.join(world)).unwrap();
+        fs::copy(fixture_path.join(world).join("sprites.toml"), assets.join(world).join("sprites.toml")).unwrap();
+    }
+
+    let mut database = Database::new();
+    database.load_custom(&assets).expect("failed to load worlds");
+    let mut watcher = AssetWatcher::new();
+    watcher.watch_custom(&assets).expect("failed to watch worlds");
+    assert!(database.reload_changed(&mut watcher).unwrap().is_empty());
+
+    // Remove alpha's version of the shared tile, recolor one tile and add another
+    let alpha_path = assets.join("alpha").join("sprites.toml");
+    fs::write(&alpha_path, r#"
+[lonely]
+color = [2, 4]
+sprite = "lonely"
+tiling = -1
+author = "alpha"
+
+[fresh]
+color = [0, 3]
+sprite = "fresh"
+tiling = -1
+author = "alpha"
+"#).unwrap();
+    fs::File::options().write(true).open(&alpha_path).unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(10)).unwrap();
+
+    let changes = database.reload_changed(&mut watcher).expect("failed to reload");
+    assert_eq!(changes.added.keys().collect::<Vec<_>>(), ["fresh"]);
+    assert!(changes.removed.is_empty());
+    assert_eq!(changes.modified.keys().collect::<Vec<_>>(), ["lonely", "shared"]);
+    // Beta's shared tile is no longer overridden
+    assert_eq!(database.tiles["shared"].sprite, "shared_beta");
+    assert!(database.conflicts().is_empty());
+    assert!(database.reload_changed(&mut watcher).unwrap().is_empty());
+
+    // A world that fails to load leaves the database as it was, and is retried next time
+    let beta_path = assets.join("beta").join("sprites.toml");
+    let beta = fs::read_to_string(&beta_path).unwrap();
+    let (tiles, worlds) = (database.tiles.clone(), database.worlds.clone());
+    fs::write(&beta_path, "[shared\ncolor = ").unwrap();
+    fs::File::options().write(true).open(&beta_path).unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(20)).unwrap();
+    database.reload_changed(&mut watcher).expect_err("reloaded a broken world");
+    assert_eq!(database.tiles, tiles);
+    assert_eq!(database.worlds, worlds);
+    fs::write(&beta_path, beta.replace("shared_beta", "shared_fixed")).unwrap();
+    fs::File::options().write(true).open(&beta_path).unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(30)).unwrap();
+    let changes = database.reload_changed(&mut watcher).expect("failed to reload a fixed world");
+    assert_eq!(changes.modified.keys().collect::<Vec<_>>(), ["shared"]);
+    assert_eq!(database.tiles["shared"].sprite, "shared_fixed");
+
+    fs::remove_dir_all(assets).unwrap();
+}
+
+#[test]
+fn hot_reload_vanilla() {
+    // Work on a copy of the game data, since we're touching it
+    let fixture_path = PathBuf::from(file!()).with_file_name("notbaba");
+    let game = std::env::temp_dir().join(format!("chilly-hot-reload-vanilla-{}", std::process::id()));
+    fs::create_dir_all(game.join("Data/Editor")).unwrap();
+    for file in ["Data/values.lua", "Data/Editor/editor_objectlist.lua"] {
+        fs::copy(fixture_path.join(file), game.join(file)).unwrap();
+    }
+    let mut custom = MemorySource::new();
+    custom.insert("alpha/sprites.toml", "[sample]\ncolor = [2, 2]\nsprite = \"sample_alpha\"\ntiling = -1\nauthor = \"alpha\"\n");
+    let custom = std::sync::Arc::new(custom);
+
+    let mut database = Database::new();
+    database.load_vanilla(&game).expect("failed to load vanilla assets");
+    database.load_custom_from(custom.as_ref()).expect("failed to load worlds");
+    assert_eq!(database.tiles["sample"].directory, "alpha");
+    let mut watcher = AssetWatcher::new();
+    watcher.watch_vanilla(&game);
+    watcher.watch_custom_from(custom.clone()).expect("failed to watch worlds");
+
+    // Reloading vanilla keeps the custom override on top, just like loading from scratch
+    let values_path = game.join("Data/values.lua");
+    fs::File::options().write(true).open(&values_path).unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(10)).unwrap();
+    database.reload_changed(&mut watcher).expect("failed to reload vanilla assets");
+    let mut fresh = Database::new();
+    fresh.load_vanilla(&game).expect("failed to load vanilla assets");
+    fresh.load_custom_from(custom.as_ref()).expect("failed to load worlds");
+    assert_eq!(database.tiles, fresh.tiles);
+    assert_eq!(database.conflicts(), fresh.conflicts());
+    assert_eq!(database.tiles["sample"].directory, "alpha");
+    assert_eq!(database.get_tile(Some("vanilla"), "sample").map(|tile| tile.directory.as_str()), Some("vanilla"));
+
+    fs::remove_dir_all(game).unwrap();
+}
+
+#[test]
+#[cfg(feature = "rendering")]
+fn palettes() {
+    let conflict_assets = PathBuf::from(file!()).with_file_name("conflicts");
+    let mut database = Database {
+        world_precedence: vec!["beta".into()],
+        ..Default::default()
+    };
+    database.load_custom(&conflict_assets).expect("failed to load worlds");
+
+    let names = database.list_palettes().iter()
+        .map(|palette| (palette.name.as_str(), palette.world.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(names, [("default", "beta"), ("default", "alpha"), ("night", "beta")]);
+
+    let default = database.get_palette(None, "default").expect("default palette is missing");
+    assert_eq!(default.world, "beta");
+    assert_eq!(default.image.get_pixel(0, 0).0, [0, 0, 255, 255]);
+    let alpha = database.get_palette(Some("alpha"), "default").expect("alpha's palette is missing");
+    assert_eq!(alpha.image.get_pixel(6, 4).0, [255, 0, 0, 255]);
+    assert!(alpha.source.ends_with("alpha/palettes/default.png"));
+    assert!(database.get_palette(Some("alpha"), "night").is_none());
+}
+
+#[test]
+fn memory_source() {
+    let mut source = MemorySource::new();
+    source.insert("alpha/sprites.toml", "[lonely]\ncolor = [0, 3]\nsprite = \"lonely\"\ntiling = -1\nauthor = \"alpha\"\n");
+    source.insert("beta/sprites.toml", "[lonely]\ncolor = [1, 3]\nsprite = \"lonely_beta\"\ntiling = -1\nauthor = \"beta\"\n");
+    source.insert("general/sprites.toml", "not a world");
+
+    let mut database = Database::new();
+    let conflicts = database.load_custom_from(&source).expect("failed to load worlds from memory");
+    assert_eq!(conflicts, vec![TileConflict {
+        name: "lonely".into(),
+        world: "alpha".into(),
+        overridden: vec!["beta".into()]
+    }]);
+    assert_eq!(database.tiles["lonely"].color, Color::Paletted { x: 0, y: 3 });
+
+    let mut watcher = AssetWatcher::new();
+    watcher.watch_custom_from(std::sync::Arc::new(source)).expect("failed to watch worlds");
+    assert!(database.reload_changed(&mut watcher).unwrap().is_empty());
+}
+
+#[test]
+#[cfg(feature = "archives")]
+fn zip_source() {
+    use std::io::{Cursor, Write};
+    use chilly::database::source::ZipSource;
+
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let sprites = fs::read(PathBuf::from(file!()).with_file_name("conflicts").join("alpha/sprites.toml")).unwrap();
+    writer.start_file("alpha/sprites.toml", zip::write::SimpleFileOptions::default()).unwrap();
+    writer.write_all(&sprites).unwrap();
+    let archive = writer.finish().unwrap();
+
+    let source = ZipSource::new(archive).expect("failed to open zip archive");
+    let mut database = Database::new();
+    database.load_custom_from(&source).expect("failed to load worlds from zip archive");
+    assert_eq!(database.tiles["shared"].sprite, "shared_alpha");
+    assert_eq!(database.tiles["lonely"].directory, "alpha");
+}
+
+#[test]
+fn world_metadata() {
+    let conflict_assets = PathBuf::from(file!()).with_file_name("conflicts");
+    let mut database = Database::new();
+    database.load_custom(&conflict_assets).expect("failed to load worlds");
+    assert_eq!(database.get_world("alpha"), Some(&World {
+        name: "alpha".into(),
+        display_name: Some("Alpha".into()),
+        authors: vec!["alpha".into(), "a friend of alpha".into()],
+        description: Some("The first world.".into()),
+        license: Some("CC0-1.0".into()),
+        default_palette: Some("default".into()),
+        priority: 0
+    }));
+    // Worlds without a world.toml still get listed
+    assert_eq!(database.get_world("beta"), Some(&World { name: "beta".into(), ..Default::default() }));
+
+    // Priority wins over alphabetical order, but not over explicit precedence
+    let mut source = MemorySource::new();
+    source.insert("alpha/sprites.toml", "[lonely]\ncolor = [0, 3]\nsprite = \"lonely\"\ntiling = -1\nauthor = \"alpha\"\n");
+    source.insert("beta/sprites.toml", "[lonely]\ncolor = [1, 3]\nsprite = \"lonely_beta\"\ntiling = -1\nauthor = \"beta\"\n");
+    source.insert("beta/world.toml", "priority = 10");
+    let mut database = Database::new();
+    database.load_custom_from(&source).expect("failed to load worlds from memory");
+    assert_eq!(database.tiles["lonely"].directory, "beta");
+    let names = database.list_worlds().iter().map(|world| world.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["beta", "alpha"]);
+
+    let mut database = Database {
+        world_precedence: vec!["alpha".into()],
+        ..Default::default()
+    };
+    database.load_custom_from(&source).expect("failed to load worlds from memory");
+    assert_eq!(database.tiles["lonely"].directory, "alpha");
+}
+
+#[test]
+fn aliases() {
+    let mut source = MemorySource::new();
+    source.insert("alpha/sprites.toml", r#"
+[keke]
+color = [2, 2]
+sprite = "keke"
+tiling = -1
+author = "alpha"
+aliases = ["kiki"]
+renamed_from = ["kek"]
+
+[lonely]
+color = [0, 3]
+sprite = "lonely"
+tiling = -1
+author = "alpha"
+aliases = ["keke"]
+"#);
+    let mut database = Database::new();
+    database.load_custom_from(&source).expect("failed to load worlds from memory");
+    // Aliases can't shadow actual tiles
+    assert!(database.get_alias("keke").is_none());
+    assert_eq!(database.get_alias("kek"), Some(&TileAlias { name: "keke".into(), deprecated: true }));
+
+    let raw = chilly::parser::parse("kiki kek alpha/kek").expect("failed to parse scene");
+    let easter_eggs = HashSet::new();
+    let scene = raw.solidify(&database, &TileDefault::Tile, &easter_eggs);
+    for skeleton in scene.map.objects.values() {
+        assert!(matches!(skeleton.data, TileSkeletonType::Existing(data) if data.sprite == "keke"));
+    }
+    assert_eq!(scene.warnings.len(), 2);
+    assert!(matches!(&scene.warnings[0], SolidifyWarning::DeprecatedName(_, old, new) if old == "kek" && new == "keke"));
+}
+
+#[test]
+fn diff() {
+    let conflict_assets = PathBuf::from(file!()).with_file_name("conflicts");
+    let mut old = Database::new();
+    old.load_custom(&conflict_assets).expect("failed to load worlds");
+    assert!(old.diff(&old).is_empty());
+
+    let mut source = MemorySource::new();
+    source.insert("alpha/sprites.toml", r#"
+[shared]
+color = [4, 1]
+sprite = "shared_alpha"
+tiling = 0
+author = "alpha"
+tags = ["new"]
+
+[fresh]
+color = [0, 3]
+sprite = "fresh"
+tiling = -1
+author = "alpha"
+"#);
+    let mut new = Database::new();
+    new.load_custom_from(&source).expect("failed to load worlds from memory");
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.added.keys().collect::<Vec<_>>(), ["fresh"]);
+    assert_eq!(diff.removed.keys().collect::<Vec<_>>(), ["lonely"]);
+    assert_eq!(diff.changed["shared"], [
+        TileChange::Color(Color::Paletted { x: 2, y: 2 }, Color::Paletted { x: 4, y: 1 }),
+        TileChange::Tiling(Tiling::None, Tiling::Directional),
+        TileChange::Tags(BTreeSet::new(), BTreeSet::from(["new".into()]))
+    ]);
+}
+
+/// Builds the start of a PNG file, up to its size.
+fn png_header(width: u32, height: u32) -> Vec<u8> {
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    png.extend(width.to_be_bytes());
+    png.extend(height.to_be_bytes());
+    png
+}
+
+#[test]
+fn sprite_metadata() {
+    let mut source = MemorySource::new();
+    source.insert("alpha/sprites.toml", r#"
+[spinner]
+color = [0, 3]
+sprite = "spinner"
+tiling = 4
+author = "alpha"
+
+[walker]
+color = [0, 3]
+sprite = "walker"
+tiling = 2
+author = "alpha"
+frame_count = 2
+"#);
+    for frame in 0 .. 3 {
+        for wobble in 1 ..= 3 {
+            source.insert(format!("alpha/sprites/spinner_{frame}_{wobble}.png"), png_header(24, 48));
+        }
+    }
+    for frame in [0, 1, 2, 3, 7, 31] {
+        source.insert(format!("alpha/sprites/walker_{frame}_1.png"), png_header(24, 24));
+    }
+    let mut database = Database::new();
+    database.load_custom_from(&source).expect("failed to load worlds from memory");
+
+    let spinner = &database.tiles["spinner"];
+    assert_eq!(spinner.sprite_size, Some((24, 48)));
+    assert_eq!(spinner.frame_count, Some(3));
+    assert_eq!(spinner.wobble_count, Some(3));
+    assert_eq!(database.sprite_files("alpha", "spinner").map(BTreeMap::len), Some(9));
+    assert_eq!(
+        database.sprite_files("alpha", "walker").unwrap().get(&(31, 1)),
+        Some(&PathBuf::from("alpha/sprites/walker_31_1.png"))
+    );
+    let walker = &database.tiles["walker"];
+    // Given values aren't replaced
+    assert_eq!(walker.frame_count, Some(2));
+    assert_eq!(walker.wobble_count, Some(1));
+
+    // Animation cycles wrap around the frames that exist
+    let raw = chilly::parser::parse("spinner:a/2 spinner:f/1:a/2 walker:u:a/3").expect("failed to parse scene");
+    let easter_eggs = HashSet::new();
+    let scene = raw.solidify(&database, &TileDefault::Tile, &easter_eggs);
+    let mut frames = scene.map.objects.iter()
+        .map(|(pos, skeleton)| (pos.x, skeleton.animation_frame.0))
+        .collect::<Vec<_>>();
+    frames.sort_unstable();
+    assert_eq!(frames, [(0, 2), (1, 0), (2, 9)]);
+
+    // Vanilla sprites are indexed by where they actually are
+    let vanilla_assets = PathBuf::from(file!()).with_file_name("notbaba");
+    let mut vanilla = MemorySource::new();
+    for file in ["Data/values.lua", "Data/Editor/editor_objectlist.lua"] {
+        vanilla.insert(file, fs::read(vanilla_assets.join(file)).unwrap());
+    }
+    vanilla.insert("Data/Sprites/sample_0_1.png", png_header(24, 24));
+    database.load_vanilla_from(&vanilla).expect("failed to load vanilla assets from memory");
+    assert_eq!(
+        database.sprite_files("vanilla", "sample").unwrap().get(&(0, 1)),
+        Some(&PathBuf::from("Data/Sprites/sample_0_1.png"))
+    );
+}
+
+#[test]
+fn shared_database() {
+    let conflict_assets = PathBuf::from(file!()).with_file_name("conflicts");
+    let mut database = Database::new();
+    database.load_custom(&conflict_assets).expect("failed to load worlds");
+    let shared = SharedDatabase::new(database);
+
+    let snapshot = shared.snapshot();
+    let lonely = &snapshot.tiles["lonely"];
+    std::thread::scope(|scope| {
+        for _ in 0 .. 4 {
+            // Readers might see either database, but never a mix of the two
+            scope.spawn(|| {
+                let snapshot = shared.snapshot();
+                assert_ne!(snapshot.tiles.contains_key("shared"), snapshot.tiles.contains_key("sample"));
+            });
+        }
+        scope.spawn(|| {
+            let mut replacement = Database::new();
+            replacement.load_vanilla(PathBuf::from(file!()).with_file_name("notbaba"))
+                .expect("failed to load vanilla assets");
+            shared.swap(replacement);
+        });
+    });
+    // The old snapshot is untouched
+    assert_eq!(lonely.author, "alpha");
+    assert!(!shared.snapshot().tiles.contains_key("lonely"));
+    assert!(shared.snapshot().tiles.contains_key("sample"));
+}
@@ -0,0 +1,44 @@
+#![cfg(all(feature = "export", feature = "assets"))]
+
+use std::path::PathBuf;
+
+use chilly::database::Database;
+
+fn load() -> Database {
+    let testing_path = PathBuf::from(file!());
+    let mut database = Database::new();
+    database.load_custom(testing_path.with_file_name("assets")).expect("failed to load custom assets");
+    database.load_vanilla(testing_path.with_file_name("notbaba")).expect("failed to load vanilla assets");
+    database
+}
+
+#[test]
+fn json() {
+    let database = load();
+    let mut json = Vec::new();
+    database.write_json(&mut json).expect("failed to write JSON");
+    let roundtrip: Database = serde_json::from_slice(&json).expect("failed to read JSON");
+    assert_eq!(roundtrip, database);
+
+    // Both forms of color survive
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.contains("\"color\": [\n        255,\n        255,\n        128\n      ]"));
+    // The output doesn't depend on hashing order
+    let mut again = Vec::new();
+    load().write_json(&mut again).unwrap();
+    assert_eq!(json.as_bytes(), again);
+}
+
+#[test]
+fn csv() {
+    let database = load();
+    let mut csv = Vec::new();
+    database.write_csv(&mut csv).expect("failed to write CSV");
+    let csv = String::from_utf8(csv).unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), database.tiles.len() + 1);
+    assert_eq!(lines[0], "name,world,sprite,color,inactive_color,tiling,author,tile_index,grid_index,object_id,layer,tags,aliases,renamed_from,sprite_size,frame_count,wobble_count");
+    assert_eq!(lines[1], "bar,sample,barsprite,#FFFF80,,3,baltdev,\"0,0\",,object999,255,,,,,,");
+    assert!(lines.contains(&"text_sample,vanilla,text_sample,\"2,2\",\"2,1\",-1,Hempuli,\"2,0\",\"1,1\",object960,20,,,,,,"));
+    assert!(lines.contains(&"sample,vanilla,sample,\"2,4\",,2,Hempuli,\"1,0\",\"0,1\",object999,18,\"tag1,tag2\",,,,,"));
+}
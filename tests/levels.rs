@@ -0,0 +1,130 @@
+#![cfg(all(feature = "levels", feature = "assets"))]
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+
+use chilly::arguments::{Flag, FlagName, PaletteName, Variant};
+use chilly::database::Database;
+use chilly::levels::{Direction, ExportedLevel, Level, LevelError, SkipReason};
+use chilly::solidify::TileDefault;
+use chilly::structures::Position;
+use flate2::{write::ZlibEncoder, Compression};
+
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Builds a level file with a single map layer.
+fn level_file(width: u32, height: u32, tiles: &[u16], directions: &[u8]) -> Vec<u8> {
+    let tiles = compress(&tiles.iter().flat_map(|id| id.to_le_bytes()).collect::<Vec<_>>());
+    let directions = compress(directions);
+    let mut layer = Vec::new();
+    layer.extend(width.to_le_bytes());
+    layer.extend(height.to_le_bytes());
+    layer.extend([0; 32]);
+    layer.push(2);
+    layer.extend(b"MAIN");
+    layer.extend(u32::try_from(tiles.len()).unwrap().to_le_bytes());
+    layer.extend(tiles);
+    layer.extend(b"DATA");
+    layer.extend([0; 5]);
+    layer.extend(u32::try_from(directions.len()).unwrap().to_le_bytes());
+    layer.extend(directions);
+
+    let mut file = Vec::new();
+    file.extend(b"ACHTUNG!");
+    file.extend(261u16.to_le_bytes());
+    file.extend(b"MAP ");
+    file.extend(2u32.to_le_bytes());
+    file.extend(0u16.to_le_bytes());
+    file.extend(b"LAYR");
+    file.extend(u32::try_from(layer.len() + 2).unwrap().to_le_bytes());
+    file.extend(1u16.to_le_bytes());
+    file.extend(layer);
+    file
+}
+
+#[test]
+fn load_level() {
+    let mut database = Database::new();
+    database.load_vanilla(PathBuf::from(file!()).with_file_name("notbaba"))
+        .expect("failed to load vanilla assets");
+
+    // sample is at tile {1, 0}, sample2 is at tile {1, 1}
+    let map = level_file(
+        3, 2,
+        &[0x0001, 0xFFFF, 0x0101, 0xFFFF, 0x0001, 0x0505],
+        &[0, 0, 0, 0, 1, 0]
+    );
+    let metadata = "[general]\nname=test level\npalette=abstract.png\n";
+    let level = Level::from_bytes(&map, Some(metadata), &database).expect("failed to read level");
+
+    assert_eq!(level.name, "test level");
+    assert_eq!(level.palette.as_deref(), Some("abstract"));
+    assert_eq!((level.width, level.height), (3, 2));
+    assert_eq!(level.objects.len(), 3);
+    assert_eq!(level.objects[2].direction, Direction::Up);
+    assert_eq!(level.unknown.len(), 1);
+    assert_eq!(level.unknown[0].id, 0x0505);
+
+    let scene = level.to_raw_scene(&database);
+    assert_eq!(scene.map.objects.len(), 3);
+    assert!(matches!(scene.flags.get(&FlagName::Palette), Some(Flag::Palette(PaletteName { world: None, name })) if name == "abstract"));
+    let facing_up = &scene.map.objects[&Position { x: 1, y: 1, z: 0, t: 0 }];
+    assert_eq!(facing_up.name, "sample");
+    assert_eq!(facing_up.variants, [Variant::Up()]);
+    assert_eq!(scene.map.objects[&Position { x: 2, y: 0, z: 0, t: 0 }].name, "sample2");
+}
+
+#[test]
+fn truncated_level() {
+    let database = Database::new();
+    let map = level_file(1, 1, &[0xFFFF], &[0]);
+    // Cut the file off partway through the layer header
+    let result = Level::from_bytes(&map[.. 22], None, &database);
+    assert!(matches!(result, Err(LevelError::UnexpectedEof(20, 4))), "{result:?}");
+}
+
+#[test]
+fn export_level() {
+    let mut database = Database::new();
+    database.load_vanilla(PathBuf::from(file!()).with_file_name("notbaba"))
+        .expect("failed to load vanilla assets");
+
+    // A custom tile with a tile index still isn't in the game
+    let mut custom = database.tiles["sample"].clone();
+    custom.directory = "custom".to_string();
+    database.tiles.insert("custom_sample".to_string(), custom);
+
+    let raw = chilly::parser::parse("-p=abstract sample:d editor_sample nothing custom_sample\nsample&sample&sample&sample")
+        .expect("failed to parse scene");
+    let easter_eggs = HashSet::new();
+    let scene = raw.solidify(&database, &TileDefault::Tile, &easter_eggs);
+    let exported = ExportedLevel::from_scene(&scene, "exported").expect("failed to export scene");
+
+    let mut reasons = exported.skipped.iter().map(|skipped| (skipped.name.as_str(), skipped.reason)).collect::<Vec<_>>();
+    reasons.sort_unstable_by_key(|(name, _)| *name);
+    assert_eq!(reasons, [
+        ("custom_sample", SkipReason::NotInGame),
+        ("editor_sample", SkipReason::NotInGame),
+        ("nothing", SkipReason::Generated),
+        ("sample", SkipReason::TooManyLayers)
+    ]);
+
+    // Read the level back in
+    let level = Level::from_bytes(&exported.map, Some(&exported.metadata), &database)
+        .expect("failed to read exported level");
+    assert_eq!(level.name, "exported");
+    assert_eq!(level.palette.as_deref(), Some("abstract"));
+    assert_eq!((level.width, level.height), (4, 2));
+    assert!(level.unknown.is_empty());
+    // editor_sample has no tile index, so it isn't in the game
+    assert_eq!(level.objects.len(), 4);
+    let facing_down = level.objects.iter().find(|object| (object.x, object.y) == (0, 0)).unwrap();
+    assert_eq!(facing_down.name, "sample");
+    assert_eq!(facing_down.direction, Direction::Down);
+    assert_eq!(level.objects.iter().filter(|object| (object.x, object.y) == (0, 1)).count(), 3);
+}
@@ -29,6 +29,20 @@ tileslist =
 		grid = {0, 2},
 		layer = 16,
 	},
+	object960 =
+	{
+		name = "text_sample",
+		sprite = "text_sample",
+		sprite_in_root = true,
+		unittype = "text",
+		tiling = -1,
+		type = 0,
+		colour = {2, 1},
+		colour_active = {2, 2},
+		tile = {2, 0},
+		grid = {1, 1},
+		layer = 20,
+	},
 }
 
 
@@ -10,11 +10,154 @@ static ARG_FAIL: &str = r"me:m/2/invalid";
 
 static VAR_FAIL: &str = r"me:dne";
 
+static NAMESPACED: &str = r"balt/balt:m baba balt/>";
+
 #[test]
 fn test_parsing() {
+    use chilly::arguments::{Flag, FlagName, PaletteName};
+
     dbg!(chilly::parser::parse(SIMPLE_SCENE).expect("failed to parse simple scene"));
     dbg!(chilly::parser::parse(FLAGS).expect("failed to parse flags"));
     dbg!(chilly::parser::parse(VAR_TEST).expect("failed to parse variant test"));
+    let namespaced = chilly::parser::parse(NAMESPACED).expect("failed to parse namespaced tiles");
+    let worlds = namespaced.map.objects.values()
+        .map(|tile| (tile.world, tile.name))
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(worlds, [(Some("balt"), "balt"), (None, "baba")].into());
+    // An empty name inherits the last tile's world only if it doesn't give its own
+    let inherited = chilly::parser::parse("balt/keke>>baba>balt/").expect("failed to parse inherited tiles");
+    let mut frames = inherited.map.objects.iter()
+        .map(|(pos, tile)| (pos.t, tile.world, tile.name))
+        .collect::<Vec<_>>();
+    frames.sort_unstable();
+    assert_eq!(frames, [
+        (0, Some("balt"), "keke"), (1, Some("balt"), "keke"),
+        (2, None, "baba"), (3, Some("balt"), "baba")
+    ]);
+    for (flags, world, name) in [("-p=night", None, "night"), ("--palette=alpha/night", Some("alpha"), "night")] {
+        let scene = chilly::parser::parse(flags).expect("failed to parse palette flag");
+        assert_eq!(
+            scene.flags.get(&FlagName::Palette),
+            Some(&Flag::Palette(PaletteName { world: world.map(str::to_string), name: name.to_string() }))
+        );
+    }
     eprintln!("{}", chilly::parser::parse(ARG_FAIL).expect_err("successfully parsed variant that had invalid argument"));
     eprintln!("{}", chilly::parser::parse(VAR_FAIL).expect_err("successfully parsed variant that doesn't exist"));
 }
+
+#[test]
+fn keyframes() {
+    use chilly::arguments::{Keyframed, Variant};
+
+    let scene = chilly::parser::parse("baba:rot/0~360~0:op/50").expect("failed to parse keyframes");
+    let tile = scene.map.objects.values().next().expect("scene should have a tile");
+    assert_eq!(tile.variants, [
+        Variant::Rotate(Keyframed(vec![0.0, 360.0, 0.0])),
+        Variant::Opacity(Keyframed(vec![50.0]))
+    ]);
+    let Variant::Rotate(rotation) = &tile.variants[0] else { unreachable!() };
+    assert_eq!(rotation.at(0.0), 0.0);
+    assert_eq!(rotation.at(0.25), 180.0);
+    assert_eq!(rotation.at(0.5), 360.0);
+    assert_eq!(rotation.at(1.0), 0.0);
+    assert_eq!(Keyframed::from(50.0).at(0.75), 50.0);
+    eprintln!("{}", chilly::parser::parse("baba:rot/0~").expect_err("parsed keyframes with a missing value"));
+}
+
+#[test]
+fn custom_variants() {
+    use chilly::arguments::{Argument, RegistryError, Variant, VariantRegistry};
+
+    #[derive(Debug, PartialEq)]
+    struct Team(String);
+
+    impl Argument for Team {
+        fn parse<'a>(mut args: impl Iterator<Item = &'a str>) -> Result<Self, Box<dyn std::error::Error>> {
+            match args.next() {
+                Some(team @ ("red" | "blue")) => Ok(Team(team.to_string())),
+                _ => Err("team must be red or blue".into())
+            }
+        }
+    }
+
+    let mut registry = VariantRegistry::new();
+    registry.register::<Team>("team", &["tm"], "Marks a tile as being on a team.").expect("failed to register variant");
+    assert_eq!(
+        registry.register::<()>("other", &["m"], "Clashes with meta."),
+        Err(RegistryError::NameTaken("m".to_string()))
+    );
+    assert_eq!(
+        registry.register::<()>("tm", &[], "Clashes with team."),
+        Err(RegistryError::NameTaken("tm".to_string()))
+    );
+    assert_eq!(registry.variants().map(|data| data.name.as_str()).collect::<Vec<_>>(), ["team"]);
+
+    let scene = chilly::parser::parse_with_variants("baba:tm/red:m", &registry).expect("failed to parse custom variant");
+    let tile = scene.map.objects.values().next().expect("scene should have a tile");
+    let Variant::Custom(custom) = &tile.variants[0] else {
+        panic!("expected a custom variant, got {:?}", tile.variants[0])
+    };
+    assert_eq!(custom.data().name, "team");
+    assert_eq!(custom.arguments::<Team>(), Some(&Team("red".to_string())));
+
+    eprintln!("{}", chilly::parser::parse_with_variants("baba:tm/green", &registry).expect_err("parsed an invalid team"));
+    eprintln!("{}", chilly::parser::parse("baba:tm/red").expect_err("parsed a custom variant without registering it"));
+
+    // Tuples take several arguments, each checked against its own schema
+    registry.register::<(Team, u8, Option<f32>)>("squad", &[], "Puts a tile in a numbered squad.")
+        .expect("failed to register variant");
+    let squad = registry.variants().find(|data| data.name == "squad").expect("squad should be registered");
+    assert_eq!(squad.arguments.len(), 3);
+    assert_eq!((squad.arguments[1].max, squad.arguments[2].optional), (Some(255.0), true));
+    let scene = chilly::parser::parse_with_variants("baba:squad/blue/3", &registry).expect("failed to parse custom variant");
+    let tile = scene.map.objects.values().next().expect("scene should have a tile");
+    let Variant::Custom(custom) = &tile.variants[0] else {
+        panic!("expected a custom variant, got {:?}", tile.variants[0])
+    };
+    assert_eq!(custom.arguments::<(Team, u8, Option<f32>)>(), Some(&(Team("blue".to_string()), 3, None)));
+    eprintln!("{}", chilly::parser::parse_with_variants("baba:squad/blue/300", &registry).expect_err("parsed an out of range squad"));
+}
+
+#[test]
+fn argument_schemas() {
+    use chilly::arguments::{ArgumentKind, VariantName};
+
+    let meta = VariantName::Meta.data();
+    assert_eq!(meta.arguments[1].kind, ArgumentKind::Choice(&["full", "edge", "unit"]));
+    assert!(meta.arguments.iter().all(|argument| argument.optional));
+    assert_eq!(meta.arguments[2].min, Some(1.0));
+    assert_eq!(VariantName::Tiling.data().arguments[0].kind.to_string(), "r|u|l|d|ur|ul|dl|dr");
+    eprintln!("{}", meta.usage());
+    eprintln!("{}", VariantName::Stripes.data().usage());
+    assert_eq!(VariantName::Scale.data().arguments[0].to_string(), "<keyframes, over 0 up to 16>");
+
+    // Arguments that grow a sprite are bounded on both sides
+    for (name, index) in [
+        (VariantName::Scale, 0), (VariantName::Wave, 0), (VariantName::Wave, 1),
+        (VariantName::VerticalWave, 0), (VariantName::VerticalWave, 1), (VariantName::Blur, 0),
+        (VariantName::Shadow, 0), (VariantName::Shadow, 1), (VariantName::Shadow, 3),
+        (VariantName::Meta, 0), (VariantName::Meta, 2)
+    ] {
+        let schema = name.data().arguments[index];
+        assert!(schema.min.is_some() || schema.above.is_some(), "{name:?}'s argument {index} has no lower bound");
+        assert!(schema.max.is_some(), "{name:?}'s argument {index} has no upper bound");
+    }
+
+    for scene in [
+        "baba:gs/150", "baba:op/50~101", "baba:pix/0", "baba:m/1/full/0",
+        "baba:rot/inf", "baba:hue/0~NaN", "baba:sc/1e39",
+        "baba:sc/1e30", "baba:sc/0", "baba:sc/-1", "baba:sc/1~17",
+        "baba:wave/1e20/5", "baba:wy/65/5", "baba:wave/1/0", "baba:wave/1/-3", "baba:blur/1e20", "baba:blur/0",
+        "baba:ds/9223372036854775807/0", "baba:ds/100000/100000", "baba:ds/1/1/0,4/17", "baba:m/127/full/255", "baba:m/9", "baba:m/1/full/9"
+    ] {
+        eprintln!("{}", chilly::parser::parse(scene).expect_err("parsed an out of bounds argument"));
+    }
+    chilly::parser::parse("baba:gs/0~100:op/50").expect("failed to parse in bounds arguments");
+
+    // Arguments after a multi-valued one are still checked
+    let mut registry = chilly::arguments::VariantRegistry::new();
+    registry.register::<([u8; 2], i8)>("pair", &[], "Takes a pair, then a signed byte.").expect("failed to register variant");
+    chilly::parser::parse_with_variants("baba:pair/1/2/-3", &registry).expect("failed to parse in bounds arguments");
+    let err = chilly::parser::parse_with_variants("baba:pair/1/2/200", &registry).expect_err("parsed an out of bounds argument");
+    eprintln!("{err}");
+}
@@ -0,0 +1,510 @@
+#![cfg(all(feature = "rendering", feature = "assets"))]
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chilly::arguments::{BlendMode, VariantName, VariantRegistry};
+use chilly::database::{source::MemorySource, Database};
+use chilly::renderer::{render, RenderedScene, RenderingError, SceneFrame, Sprite};
+use chilly::solidify::TileDefault;
+use image::{ImageFormat, Rgba, RgbaImage};
+
+fn png(image: &RgbaImage) -> Vec<u8> {
+    let mut png = Cursor::new(Vec::new());
+    image.write_to(&mut png, ImageFormat::Png).unwrap();
+    png.into_inner()
+}
+
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+const GREEN: Rgba<u8> = Rgba([0, 255, 0, 255]);
+const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+/// Builds a world with a few simple tiles, all tinted white so their sprites keep their colors:
+/// - `spinner`, an animated tile with blank sprites
+/// - `quadrants`, split into red, blue, green and white quarters, clockwise from the top left
+/// - `block`, a solid white square
+/// - `dot`, a white 4x4 square in the middle of the sprite
+///
+/// In the palette, `0,0` is black, `0,3` is white, `0,4` is dark gray, `2,2` is red and `3,3` is blue.
+fn test_database() -> (MemorySource, Database) {
+    let mut source = MemorySource::new();
+    source.insert("alpha/sprites.toml", r#"
+[spinner]
+color = [0, 3]
+sprite = "spinner"
+tiling = 4
+author = "alpha"
+
+[quadrants]
+color = [0, 3]
+sprite = "quadrants"
+tiling = -1
+author = "alpha"
+
+[block]
+color = [0, 3]
+sprite = "block"
+tiling = -1
+author = "alpha"
+
+[dot]
+color = [0, 3]
+sprite = "dot"
+tiling = -1
+author = "alpha"
+"#);
+    let mut palette = RgbaImage::from_pixel(7, 5, Rgba([128, 128, 128, 255]));
+    palette.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+    palette.put_pixel(0, 3, WHITE);
+    palette.put_pixel(0, 4, Rgba([32, 32, 32, 255]));
+    palette.put_pixel(2, 2, RED);
+    palette.put_pixel(3, 3, BLUE);
+    source.insert("alpha/palettes/default.png", png(&palette));
+    let sprites = [
+        ("quadrants", RgbaImage::from_fn(24, 24, |x, y| match (x < 12, y < 12) {
+            (true, true) => RED,
+            (false, true) => BLUE,
+            (false, false) => WHITE,
+            (true, false) => GREEN
+        })),
+        ("block", RgbaImage::from_pixel(24, 24, WHITE)),
+        ("dot", RgbaImage::from_fn(24, 24, |x, y| {
+            if (10 .. 14).contains(&x) && (10 .. 14).contains(&y) { WHITE } else { CLEAR }
+        }))
+    ];
+    for wobble in 1 ..= 3 {
+        for frame in 0 .. 3 {
+            source.insert(format!("alpha/sprites/spinner_{frame}_{wobble}.png"), png(&RgbaImage::new(24, 24)));
+        }
+        for (name, image) in &sprites {
+            source.insert(format!("alpha/sprites/{name}_0_{wobble}.png"), png(image));
+        }
+    }
+    // A bigger dot in another world, which the first world's dot overrides
+    source.insert("beta/sprites.toml", "[dot]\ncolor = [0, 3]\nsprite = \"dot\"\ntiling = -1\nauthor = \"beta\"\n");
+    let big_dot = RgbaImage::from_fn(24, 24, |x, y| {
+        if (4 .. 20).contains(&x) && (4 .. 20).contains(&y) { WHITE } else { CLEAR }
+    });
+    for wobble in 1 ..= 3 {
+        source.insert(format!("beta/sprites/dot_0_{wobble}.png"), png(&big_dot));
+    }
+    let mut database = Database::new();
+    database.load_custom_from(&source).expect("failed to load worlds from memory");
+    (source, database)
+}
+
+/// Renders a scene with the test database, using custom variants from a registry.
+fn render_with(scene: &str, registry: &VariantRegistry) -> RenderedScene<'static> {
+    let (source, database) = test_database();
+    let raw = chilly::parser::parse_with_variants(scene, registry).expect("failed to parse scene");
+    let easter_eggs = HashSet::new();
+    let scene = raw.solidify(&database, &TileDefault::Tile, &easter_eggs);
+    render(scene, &database, &source, None::<&mut HashMap<PathBuf, RgbaImage>>).expect("failed to render scene")
+}
+
+/// Renders a scene with a single tile, returning that tile's sprite on the first frame.
+fn render_sprite(scene: &str) -> Sprite<'static> {
+    let mut rendered = render_with(scene, &VariantRegistry::new());
+    assert_eq!(rendered.frames[0].sprites.len(), 1, "expected a single sprite");
+    rendered.frames.swap_remove(0).sprites.remove(0)
+}
+
+/// Renders a scene and composites its first frame.
+fn render_image(scene: &str) -> RgbaImage {
+    render_with(scene, &VariantRegistry::new()).composite().swap_remove(0)
+}
+
+/// Renders a scene with the test database, expecting it to fail.
+fn render_error(scene: &str, registry: &VariantRegistry, check: impl FnOnce(&RenderingError)) {
+    let (source, database) = test_database();
+    let raw = chilly::parser::parse_with_variants(scene, registry).expect("failed to parse scene");
+    let easter_eggs = HashSet::new();
+    let scene = raw.solidify(&database, &TileDefault::Tile, &easter_eggs);
+    match render(scene, &database, &source, None::<&mut HashMap<PathBuf, RgbaImage>>) {
+        Ok(_) => panic!("rendered a scene that should've failed"),
+        Err(err) => check(&err)
+    }
+}
+
+#[test]
+fn vanilla_sprites() {
+    // Vanilla sprites are read from the game's own sprite directory
+    let vanilla_assets = PathBuf::from(file!()).with_file_name("notbaba");
+    let mut source = MemorySource::new();
+    for file in ["Data/values.lua", "Data/Editor/editor_objectlist.lua"] {
+        source.insert(file, std::fs::read(vanilla_assets.join(file)).unwrap());
+    }
+    let mut palette = RgbaImage::from_pixel(7, 5, Rgba([128, 128, 128, 255]));
+    // The editor's object list gives the sample tile this color
+    palette.put_pixel(2, 4, RED);
+    source.insert("Data/Palettes/default.png", png(&palette));
+    for wobble in 1 ..= 3 {
+        source.insert(format!("Data/Sprites/sample_0_{wobble}.png"), png(&RgbaImage::from_pixel(24, 24, WHITE)));
+    }
+    let mut database = Database::new();
+    database.load_vanilla_from(&source).expect("failed to load vanilla assets from memory");
+
+    let raw = chilly::parser::parse("sample").expect("failed to parse scene");
+    let easter_eggs = HashSet::new();
+    let scene = raw.solidify(&database, &TileDefault::Tile, &easter_eggs);
+    let rendered = render(scene, &database, &source, None::<&mut HashMap<PathBuf, RgbaImage>>)
+        .expect("failed to render scene");
+    let sprite = &rendered.frames[0].sprites[0];
+    assert!(sprite.image.pixels().all(|pixel| pixel == &RED), "sample should be tinted red");
+}
+
+#[test]
+fn missing_frame() {
+    render_error("spinner:f/5", &VariantRegistry::new(), |err| assert!(matches!(
+        err,
+        RenderingError::SpriteMissingFrame(_, sprite, 5, 1) if sprite == "spinner"
+    )));
+}
+
+#[test]
+fn flip_rotate_scale() {
+    let plain = render_sprite("quadrants");
+    assert_eq!((plain.image.get_pixel(0, 0), plain.image.get_pixel(23, 23)), (&RED, &WHITE));
+    assert_eq!(plain.position, (0, 0));
+
+    let flipped = render_sprite("quadrants:flipx");
+    assert_eq!((flipped.image.get_pixel(0, 0), flipped.image.get_pixel(23, 0)), (&BLUE, &RED));
+    let flipped = render_sprite("quadrants:flipy");
+    assert_eq!((flipped.image.get_pixel(0, 0), flipped.image.get_pixel(0, 23)), (&GREEN, &RED));
+
+    // Rotation is clockwise
+    let rotated = render_sprite("quadrants:rot/90");
+    assert_eq!((rotated.image.get_pixel(0, 0), rotated.image.get_pixel(23, 0)), (&GREEN, &RED));
+    // Other angles grow the sprite to fit, keeping it centered on its tile
+    let rotated = render_sprite("quadrants:rot/45");
+    assert_eq!(rotated.image.dimensions(), (34, 34));
+    assert_eq!(rotated.position, (-5, -5));
+    assert_eq!(rotated.image.get_pixel(0, 0), &CLEAR);
+
+    let scaled = render_sprite("quadrants:scale/2:sc/1.5");
+    assert_eq!(scaled.size, 3.0);
+    assert_eq!(scaled.image.dimensions(), (24, 24));
+
+    // Each scale is in bounds, but together they're too large
+    render_error("spinner:scale/16:scale/2", &VariantRegistry::new(), |err| assert!(matches!(
+        err,
+        RenderingError::SpriteInvalidVariant(_, VariantName::Scale, _)
+    )));
+}
+
+#[test]
+fn keyframes_per_frame() {
+    // Keyframes go from their first value on the first frame to their last on the last
+    let rendered = render_with("quadrants:rot/0~90>>", &VariantRegistry::new());
+    assert_eq!(rendered.frames.len(), 3);
+    let (first, last) = (&rendered.frames[0].sprites[0], &rendered.frames[2].sprites[0]);
+    assert_eq!(first.image, render_sprite("quadrants").image);
+    assert_eq!(last.image, render_sprite("quadrants:rot/90").image);
+    assert_ne!(first.image, last.image);
+
+    let frames = render_with("block:op/0~100>>", &VariantRegistry::new()).composite();
+    let alphas = frames.iter().map(|frame| frame.get_pixel(12, 12).0[3]).collect::<Vec<_>>();
+    assert_eq!(alphas[0], 0);
+    assert!(alphas[1] > 0 && alphas[1] < 255, "expected a translucent middle frame, got {alphas:?}");
+    assert_eq!(alphas[2], 255);
+}
+
+#[test]
+fn blending() {
+    let sprite = |color: [u8; 4], z_order, opacity, blend| Sprite {
+        size: 1.0,
+        z_order,
+        position: (0, 0),
+        anchor: (0, 0),
+        image: Cow::Owned(RgbaImage::from_pixel(1, 1, Rgba(color))),
+        opacity,
+        blend
+    };
+    let scene = |top| RenderedScene {
+        background: Rgba([0, 0, 0, 255]),
+        flags: HashMap::new(),
+        width: 1,
+        height: 1,
+        frames: vec![SceneFrame {
+            length: Duration::from_millis(200),
+            sprites: vec![top, sprite([200, 100, 0, 255], 0, 1.0, BlendMode::Normal)]
+        }],
+        loops: true
+    };
+    let composite = |top| *scene(top).composite()[0].get_pixel(0, 0);
+    assert_eq!(composite(sprite([0, 0, 200, 255], 1, 1.0, BlendMode::Normal)), Rgba([0, 0, 200, 255]));
+    assert_eq!(composite(sprite([0, 0, 200, 255], 1, 0.5, BlendMode::Normal)), Rgba([100, 50, 100, 255]));
+    assert_eq!(composite(sprite([100, 200, 100, 255], 1, 1.0, BlendMode::Add)), Rgba([255, 255, 100, 255]));
+    assert_eq!(composite(sprite([255, 0, 255, 255], 1, 1.0, BlendMode::Multiply)), Rgba([200, 0, 0, 255]));
+    assert_eq!(composite(sprite([100, 200, 0, 255], 1, 1.0, BlendMode::Subtract)), Rgba([100, 0, 0, 255]));
+    // Fully transparent sprites leave what's below them alone
+    assert_eq!(composite(sprite([0, 0, 200, 0], 1, 1.0, BlendMode::Screen)), Rgba([200, 100, 0, 255]));
+    // Huge sprites only draw the part that's on the canvas
+    let huge = Sprite { size: 1e30, ..sprite([0, 0, 200, 255], 1, 1.0, BlendMode::Normal) };
+    assert_eq!(composite(huge), Rgba([0, 0, 200, 255]));
+    let shrunk = Sprite { size: 0.0, ..sprite([0, 0, 200, 255], 1, 1.0, BlendMode::Normal) };
+    assert_eq!(composite(shrunk), Rgba([200, 100, 0, 255]));
+}
+
+#[test]
+fn composite_scene() {
+    // A plain background flag uses the palette's black
+    let image = render_image("-b quadrants:op/50");
+    assert_eq!(image.dimensions(), (24, 24));
+    assert_eq!(image.get_pixel(0, 0), &Rgba([128, 0, 0, 255]));
+
+    let image = render_image("-b quadrants&quadrants:flipx:blend/add");
+    assert_eq!(image.get_pixel(0, 0), &Rgba([255, 0, 255, 255]));
+    assert_eq!(image.get_pixel(23, 23), &WHITE);
+
+    // Scaled sprites grow out from the center of their own tile
+    let image = render_image("-b dot dot:scale/2");
+    assert_eq!(image.dimensions(), (48, 24));
+    for (x, y, expected) in [
+        (10, 10, WHITE), (9, 9, Rgba([0, 0, 0, 255])),
+        (32, 8, WHITE), (39, 15, WHITE), (31, 8, Rgba([0, 0, 0, 255])), (40, 15, Rgba([0, 0, 0, 255]))
+    ] {
+        assert_eq!(image.get_pixel(x, y), &expected, "wrong color at {x}, {y}");
+    }
+}
+
+#[test]
+fn color_filters() {
+    let corner = |scene: &str| *render_sprite(scene).image.get_pixel(0, 0);
+    assert_eq!(corner("quadrants:inv"), Rgba([0, 255, 255, 255]));
+    assert_eq!(corner("quadrants:gs"), Rgba([54, 54, 54, 255]));
+    assert_eq!(corner("quadrants:gs/50"), Rgba([155, 27, 27, 255]));
+    assert_eq!(corner("quadrants:bright/50"), Rgba([128, 0, 0, 255]));
+    assert_eq!(corner("quadrants:contrast/0"), Rgba([128, 128, 128, 255]));
+    assert_eq!(corner("quadrants:sat/0"), Rgba([54, 54, 54, 255]));
+    assert_eq!(corner("quadrants:hue/360"), RED);
+    // Hue shifts turn red towards cyan, but leave white alone
+    let Rgba([r, g, b, _]) = corner("quadrants:hue/180");
+    assert!(r < g && r < b, "red wasn't shifted: {:?}", [r, g, b]);
+    assert_eq!(render_sprite("quadrants:hue/180").image.get_pixel(23, 23), &WHITE);
+    // Filters apply in order
+    assert_eq!(corner("quadrants:inv:gs"), Rgba([201, 201, 201, 255]));
+}
+
+#[test]
+fn gradients_and_stripes() {
+    let stripes = render_sprite("block:stripes/2,2/3,3/0,3");
+    for (y, expected) in [(0, RED), (7, RED), (8, BLUE), (15, BLUE), (16, WHITE), (23, WHITE)] {
+        assert_eq!(stripes.image.get_pixel(5, y), &expected, "wrong stripe at row {y}");
+    }
+    // Stripes only tint, so transparent pixels stay transparent
+    let stripes = render_sprite("dot:stripes/2,2/3,3");
+    assert_eq!(stripes.image.get_pixel(0, 0), &CLEAR);
+    assert_eq!(stripes.image.get_pixel(10, 10), &RED);
+    assert_eq!(stripes.image.get_pixel(13, 13), &BLUE);
+
+    let horizontal = render_sprite("block:hgrad/2,2/3,3");
+    let (left, right) = (*horizontal.image.get_pixel(0, 0), *horizontal.image.get_pixel(23, 0));
+    assert_eq!((left, right), (Rgba([250, 0, 5, 255]), Rgba([5, 0, 250, 255])));
+    assert_eq!(horizontal.image.get_pixel(0, 23), &left);
+    let vertical = render_sprite("block:vgrad/2,2/3,3");
+    assert_eq!((vertical.image.get_pixel(23, 0), vertical.image.get_pixel(0, 23)), (&left, &right));
+    assert_eq!(render_sprite("block:grad/2,2/3,3/90").image, vertical.image);
+    assert_eq!(render_sprite("block:grad/2,2/3,3").image, horizontal.image);
+    // A later color replaces the gradient
+    assert_eq!(render_sprite("block:hgrad/2,2/3,3:2,2").image.get_pixel(23, 0), &RED);
+
+    render_error("spinner:stripes", &VariantRegistry::new(), |err| assert!(matches!(
+        err,
+        RenderingError::SpriteInvalidVariant(_, VariantName::Stripes, _)
+    )));
+}
+
+#[test]
+fn distortions() {
+    // Waves grow the sprite by their amplitude, and don't move rows at the start of each wavelength
+    let wave = render_sprite("quadrants:wave/3/12");
+    assert_eq!((wave.image.dimensions(), wave.position), ((30, 24), (-3, 0)));
+    assert_eq!((wave.image.get_pixel(2, 0), wave.image.get_pixel(3, 0)), (&CLEAR, &RED));
+    assert_eq!((wave.image.get_pixel(5, 3), wave.image.get_pixel(6, 3)), (&CLEAR, &RED));
+    let wave = render_sprite("quadrants:vwave/3/12");
+    assert_eq!((wave.image.dimensions(), wave.position), ((24, 30), (0, -3)));
+    assert_eq!((wave.image.get_pixel(0, 2), wave.image.get_pixel(0, 3)), (&CLEAR, &RED));
+
+    assert_eq!(render_sprite("quadrants:pix/12").image, render_sprite("quadrants").image);
+    let pixelated = render_sprite("quadrants:pix/24");
+    assert!(pixelated.image.pixels().all(|pixel| pixel == &Rgba([127, 127, 127, 255])));
+
+    let blurred = render_sprite("quadrants:blur/1");
+    assert_eq!((blurred.image.dimensions(), blurred.position), ((30, 30), (-3, -3)));
+    // The kernel doesn't quite sum to one, so this isn't exactly red
+    let Rgba([r, g, b, a]) = *blurred.image.get_pixel(9, 9);
+    assert!(r > 240 && g == 0 && b == 0 && a > 240, "expected red, got {:?}", [r, g, b, a]);
+    assert_eq!(blurred.image.get_pixel(0, 0).0[3], 0);
+    let blurred = render_sprite("quadrants:bblur/2");
+    assert_eq!((blurred.image.dimensions(), blurred.position), ((28, 28), (-2, -2)));
+    assert_eq!(blurred.image.get_pixel(8, 8), &RED);
+    // Two of the five pixels averaged here are red, and the rest are blue
+    assert_eq!(blurred.image.get_pixel(14, 8), &Rgba([102, 0, 153, 255]));
+
+    // Transparent pixels don't darken the edges of what's averaged
+    for (scene, edge) in [("dot:2,2:blur/1", (12, 15)), ("dot:2,2:bblur/1", (10, 12)), ("dot:2,2:pix/12", (0, 0))] {
+        let Rgba([r, g, b, a]) = *render_sprite(scene).image.get_pixel(edge.0, edge.1);
+        assert_eq!([r, g, b], [255, 0, 0], "{scene} darkened its edge");
+        assert!(a > 0 && a < 255, "{scene} should have a translucent edge, got an alpha of {a}");
+    }
+
+    assert_eq!(render_sprite("dot:noise/0").image, render_sprite("dot").image);
+    let noisy = render_sprite("dot:noise/50");
+    assert_ne!(noisy.image, render_sprite("dot").image);
+    assert_eq!(noisy.image, render_sprite("dot:noise/50").image, "noise should be the same every time");
+    assert_eq!(noisy.image.get_pixel(0, 0).0[3], 0);
+    assert_eq!(noisy.image.get_pixel(12, 12).0[3], 255);
+
+    // Sprites can only grow so far, no matter how they got there
+    render_error("block:pad/255/0/255/0:pad/255/0/255/0:wave/64/5", &VariantRegistry::new(), |err| assert!(matches!(
+        err,
+        RenderingError::SpriteInvalidVariant(_, VariantName::Wave, _)
+    )));
+}
+
+#[test]
+fn crop_pad_mask() {
+    let cropped = render_sprite("quadrants:crop/4/4/8/8");
+    assert_eq!((cropped.image.dimensions(), cropped.position), ((8, 8), (4, 4)));
+    assert!(cropped.image.pixels().all(|pixel| pixel == &RED));
+    let cropped = render_sprite("quadrants:crop/8/8/8/8");
+    assert_eq!((cropped.image.get_pixel(0, 0), cropped.image.get_pixel(7, 0)), (&RED, &BLUE));
+    assert_eq!((cropped.image.get_pixel(0, 7), cropped.image.get_pixel(7, 7)), (&GREEN, &WHITE));
+
+    let padded = render_sprite("quadrants:pad/1/2/3/4");
+    assert_eq!((padded.image.dimensions(), padded.position), ((28, 30), (-1, -2)));
+    assert_eq!((padded.image.get_pixel(0, 1), padded.image.get_pixel(1, 2)), (&CLEAR, &RED));
+    assert_eq!((padded.image.get_pixel(24, 25), padded.image.get_pixel(25, 26)), (&WHITE, &CLEAR));
+
+    // Masks keep the sprite where the other tile's sprite is opaque
+    let masked = render_sprite("block:mask/dot");
+    assert_eq!(masked.image.dimensions(), (24, 24));
+    assert_eq!((masked.image.get_pixel(9, 9).0[3], masked.image.get_pixel(10, 10)), (0, &WHITE));
+    assert_eq!((masked.image.get_pixel(13, 13), masked.image.get_pixel(14, 14).0[3]), (&WHITE, 0));
+    // Masks line up with the grid, even if the sprite was moved
+    let masked = render_sprite("quadrants:crop/8/8/8/8:mask/dot");
+    assert_eq!((masked.image.get_pixel(1, 1).0[3], masked.image.get_pixel(2, 2)), (0, &RED));
+    assert_eq!((masked.image.get_pixel(5, 5), masked.image.get_pixel(6, 6).0[3]), (&WHITE, 0));
+
+    // Masks can take their tile from a specific world
+    assert_eq!(render_sprite("block:mask/alpha/dot").image, render_sprite("block:mask/dot").image);
+    let masked = render_sprite("block:mask/beta/dot");
+    assert_eq!((masked.image.get_pixel(3, 3).0[3], masked.image.get_pixel(4, 4)), (0, &WHITE));
+    assert_eq!((masked.image.get_pixel(19, 19), masked.image.get_pixel(20, 20).0[3]), (&WHITE, 0));
+
+    for (scene, expected) in [
+        ("spinner:mask/nothing", VariantName::Mask),
+        ("spinner:mask/beta/quadrants", VariantName::Mask),
+        ("spinner:crop/20/0/8/8", VariantName::Crop)
+    ] {
+        render_error(scene, &VariantRegistry::new(), |err| assert!(matches!(
+            err,
+            RenderingError::SpriteInvalidVariant(_, name, _) if *name == expected
+        )));
+    }
+}
+
+#[test]
+fn outlines_and_shadows() {
+    // Outlines grow the sprite, keeping it centered on its tile
+    let outlined = render_sprite("dot:meta/1/full/1/2,2");
+    assert_eq!((outlined.image.dimensions(), outlined.position), ((26, 26), (-1, -1)));
+    assert_eq!((outlined.image.get_pixel(10, 10), outlined.image.get_pixel(11, 11)), (&RED, &WHITE));
+    assert_eq!((outlined.image.get_pixel(15, 15), outlined.image.get_pixel(16, 16)), (&RED, &CLEAR));
+    // Rounded kernels leave out the corners
+    let outlined = render_sprite("dot:meta/1/edge/1/2,2");
+    assert_eq!((outlined.image.get_pixel(10, 10), outlined.image.get_pixel(11, 10)), (&CLEAR, &RED));
+    // Thicker outlines grow the sprite further
+    let outlined = render_sprite("dot:meta/1/full/2/2,2");
+    assert_eq!((outlined.image.dimensions(), outlined.position), ((28, 28), (-2, -2)));
+    assert_eq!((outlined.image.get_pixel(9, 9), outlined.image.get_pixel(10, 10)), (&CLEAR, &RED));
+    // Without a color, the outline is tinted with the rest of the sprite
+    let outlined = render_sprite("dot:meta:3,3");
+    assert_eq!((outlined.image.get_pixel(10, 10), outlined.image.get_pixel(11, 11)), (&BLUE, &BLUE));
+    // Colored outlines keep their color over any tint before them
+    let outlined = render_sprite("dot:3,3:meta/1/full/1/2,2");
+    assert_eq!((outlined.image.get_pixel(10, 10), outlined.image.get_pixel(11, 11)), (&RED, &BLUE));
+
+    let shadowed = render_sprite("dot:shadow/2/2/2,2");
+    assert_eq!((shadowed.image.dimensions(), shadowed.position), ((26, 26), (0, 0)));
+    assert_eq!((shadowed.image.get_pixel(13, 13), shadowed.image.get_pixel(14, 14)), (&WHITE, &RED));
+    assert_eq!((shadowed.image.get_pixel(15, 15), shadowed.image.get_pixel(16, 16)), (&RED, &CLEAR));
+    // Shadows default to a dark gray, and can go up and to the left
+    let shadowed = render_sprite("dot:shadow/-2/-2");
+    assert_eq!((shadowed.image.dimensions(), shadowed.position), ((26, 26), (-2, -2)));
+    assert_eq!(shadowed.image.get_pixel(10, 10), &Rgba([32, 32, 32, 255]));
+    assert_eq!(shadowed.image.get_pixel(12, 12), &WHITE);
+    // Spreading the shadow grows it on every side
+    let shadowed = render_sprite("dot:shadow/2/2/2,2/1");
+    assert_eq!((shadowed.image.dimensions(), shadowed.position), ((28, 28), (-1, -1)));
+    assert_eq!((shadowed.image.get_pixel(17, 16), shadowed.image.get_pixel(17, 17)), (&RED, &CLEAR));
+
+    for (scene, expected) in [
+        ("spinner:meta/1/edge/1/9,9", VariantName::Meta),
+        ("spinner:shadow/2/2/9,9", VariantName::Shadow),
+        // Outlines and shadows can't grow sprites past the limit either
+        ("block:pad/255/0/255/0:pad/255/0/255/0:meta/8/full/8", VariantName::Meta),
+        ("block:pad/255/0/255/0:pad/255/0/255/0:shadow/64/0", VariantName::Shadow)
+    ] {
+        render_error(scene, &VariantRegistry::new(), |err| assert!(matches!(
+            err,
+            RenderingError::SpriteInvalidVariant(_, name, _) if *name == expected
+        )));
+    }
+}
+
+#[test]
+fn variant_defaults() {
+    // Left out arguments take the defaults their schemas advertise
+    for (short, full) in [
+        ("dot:meta", "dot:meta/1/full/1"),
+        ("dot:shadow", "dot:shadow/1/1/0,4/0"),
+        ("quadrants:gs", "quadrants:gs/100"),
+        ("block:grad/2,2/3,3", "block:grad/2,2/3,3/0")
+    ] {
+        let (short_sprite, full_sprite) = (render_sprite(short), render_sprite(full));
+        assert_eq!(short_sprite.image, full_sprite.image, "{short} and {full} should render the same");
+        assert_eq!(short_sprite.position, full_sprite.position, "{short} and {full} should render the same");
+    }
+}
+
+#[test]
+fn custom_variant_transform() {
+    let mut registry = VariantRegistry::new();
+    registry.register_with_transform::<(u8, u8), _>("band", &[], "Paints a band of rows red.", |(top, bottom), image| {
+        for y in u32::from(*top) .. u32::from(*bottom).min(image.height()) {
+            for x in 0 .. image.width() {
+                image.put_pixel(x, y, RED);
+            }
+        }
+        Ok(())
+    }).expect("failed to register variant");
+    let band = |scene| render_with(scene, &registry).frames.swap_remove(0).sprites.remove(0);
+    let sprite = band("block:band/4/8");
+    assert_eq!((sprite.image.get_pixel(0, 3), sprite.image.get_pixel(0, 4)), (&WHITE, &RED));
+    assert_eq!((sprite.image.get_pixel(23, 7), sprite.image.get_pixel(23, 8)), (&RED, &WHITE));
+    // Transforms happen before the sprite is tinted
+    let sprite = band("block:band/4/8:3,3");
+    assert_eq!((sprite.image.get_pixel(0, 3), sprite.image.get_pixel(0, 4)), (&BLUE, &Rgba([0, 0, 0, 255])));
+}
+
+#[test]
+fn custom_variant_error() {
+    let mut registry = VariantRegistry::new();
+    registry.register_with_transform::<u8, _>("shrink", &[], "Fails on purpose.", |amount, image| {
+        if u32::from(*amount) >= image.width() {
+            return Err(format!("can't shrink a sprite {} pixels wide by {amount}", image.width()));
+        }
+        Ok(())
+    }).expect("failed to register variant");
+    render_error("spinner:shrink/30", &registry, |err| assert!(matches!(
+        err,
+        RenderingError::SpriteInvalidCustomVariant(_, name, _) if name == "shrink"
+    )));
+}